@@ -0,0 +1,62 @@
+//! Compiling a regex per call vs the cached patterns in `utils::validation`.
+//!
+//! Run with `cargo bench --bench validation`.
+
+use std::hint::black_box;
+
+use cland_rust_share::utils::validation::{self, EMAIL_PATTERN, PHONE_PATTERN, Validator};
+use criterion::{Criterion, criterion_group, criterion_main};
+use regex::Regex;
+
+const EMAIL: &str = "user.name+tag@example.com";
+const PHONE: &str = "+86 (010) 1234-5678";
+
+fn email(c: &mut Criterion) {
+    let mut group = c.benchmark_group("email");
+    group.bench_function("compile_per_call", |b| {
+        b.iter(|| {
+            Regex::new(EMAIL_PATTERN)
+                .unwrap()
+                .is_match(black_box(EMAIL))
+        })
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| validation::is_valid_email(black_box(EMAIL)))
+    });
+    group.finish();
+}
+
+fn phone(c: &mut Criterion) {
+    let mut group = c.benchmark_group("phone");
+    group.bench_function("compile_per_call", |b| {
+        b.iter(|| {
+            Regex::new(PHONE_PATTERN)
+                .unwrap()
+                .is_match(black_box(PHONE))
+        })
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| validation::is_valid_phone(black_box(PHONE)))
+    });
+    group.finish();
+}
+
+fn custom_pattern(c: &mut Criterion) {
+    let pattern = r"^SKU-[0-9]{4}-[A-Z]{2}$";
+    let validator = Validator::new().with_pattern("sku", pattern).unwrap();
+    let mut group = c.benchmark_group("custom_pattern");
+    group.bench_function("compile_per_call", |b| {
+        b.iter(|| {
+            Regex::new(pattern)
+                .unwrap()
+                .is_match(black_box("SKU-0042-CN"))
+        })
+    });
+    group.bench_function("validator", |b| {
+        b.iter(|| validator.is_match("sku", black_box("SKU-0042-CN")))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, email, phone, custom_pattern);
+criterion_main!(benches);
@@ -0,0 +1,347 @@
+//! Derive macros for `cland-rust-share`.
+//!
+//! Use them through the main crate, which re-exports them next to the traits
+//! they implement: `use cland_rust_share::Validate;`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Expr, Fields, LitStr, Path, Type, meta::ParseNestedMeta, parse_macro_input,
+};
+
+/// Derive `cland_rust_share::Validate` from `#[validate(...)]` field attributes
+///
+/// Rules: `email`, `phone`, `url`, `length(min = .., max = ..)`,
+/// `range(min = .., max = ..)`, `regex = ".."`, `custom(path::to_fn)` and
+/// `nested`. Every rule except `nested` and `custom` accepts
+/// `message = ".."` and `code = ".."` to override what is recorded. Rules on
+/// `Option<T>` fields only run when the value is present.
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn derive_validate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum Rule {
+    Email(Overrides),
+    Phone(Overrides),
+    Url(Overrides),
+    Length {
+        min: Option<Expr>,
+        max: Option<Expr>,
+        overrides: Overrides,
+    },
+    Range {
+        min: Option<Expr>,
+        max: Option<Expr>,
+        overrides: Overrides,
+    },
+    Regex {
+        pattern: LitStr,
+        overrides: Overrides,
+    },
+    Custom(Path),
+    Nested,
+}
+
+#[derive(Default)]
+struct Overrides {
+    code: Option<LitStr>,
+    message: Option<LitStr>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "Validate can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Validate can only be derived for structs",
+            ));
+        }
+    };
+
+    let mut checks = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let name = ident.to_string();
+        let mut rules = Vec::new();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("validate")) {
+            attr.parse_nested_meta(|meta| {
+                rules.push(parse_rule(&meta)?);
+                Ok(())
+            })?;
+        }
+        if rules.is_empty() {
+            continue;
+        }
+        let body: Vec<TokenStream2> = rules.iter().map(|r| expand_rule(&name, r)).collect();
+        let check = if is_option(&field.ty) {
+            quote! {
+                if let ::std::option::Option::Some(__value) = &self.#ident {
+                    #(#body)*
+                }
+            }
+        } else {
+            quote! {
+                {
+                    let __value = &self.#ident;
+                    #(#body)*
+                }
+            }
+        };
+        checks.push(check);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::cland_rust_share::utils::validation_errors::Validate
+            for #name #ty_generics #where_clause
+        {
+            fn validate(
+                &self,
+            ) -> ::std::result::Result<(), ::cland_rust_share::utils::validation_errors::ValidationErrors>
+            {
+                let mut __errors = ::cland_rust_share::utils::validation_errors::ValidationErrors::new();
+                #(#checks)*
+                __errors.into_result()
+            }
+        }
+    })
+}
+
+fn parse_rule(meta: &ParseNestedMeta) -> syn::Result<Rule> {
+    let Some(ident) = meta.path.get_ident() else {
+        return Err(meta.error("expected a validation rule"));
+    };
+    let rule = match ident.to_string().as_str() {
+        "email" => Rule::Email(parse_overrides(meta)?),
+        "phone" => Rule::Phone(parse_overrides(meta)?),
+        "url" => Rule::Url(parse_overrides(meta)?),
+        "length" | "range" => {
+            let (mut min, mut max) = (None, None);
+            let mut overrides = Overrides::default();
+            meta.parse_nested_meta(|inner| {
+                if inner.path.is_ident("min") {
+                    min = Some(inner.value()?.parse()?);
+                } else if inner.path.is_ident("max") {
+                    max = Some(inner.value()?.parse()?);
+                } else {
+                    parse_override(&inner, &mut overrides)?;
+                }
+                Ok(())
+            })?;
+            if min.is_none() && max.is_none() {
+                return Err(meta.error("expected `min` and/or `max`"));
+            }
+            if ident == "length" {
+                Rule::Length {
+                    min,
+                    max,
+                    overrides,
+                }
+            } else {
+                Rule::Range {
+                    min,
+                    max,
+                    overrides,
+                }
+            }
+        }
+        "regex" => {
+            let mut pattern: Option<LitStr> = None;
+            let mut overrides = Overrides::default();
+            if meta.input.peek(syn::Token![=]) {
+                pattern = Some(meta.value()?.parse()?);
+            } else {
+                meta.parse_nested_meta(|inner| {
+                    if inner.path.is_ident("pattern") {
+                        pattern = Some(inner.value()?.parse()?);
+                    } else {
+                        parse_override(&inner, &mut overrides)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            let pattern = pattern.ok_or_else(|| meta.error("expected `regex = \"...\"`"))?;
+            if let Err(err) = regex::Regex::new(&pattern.value()) {
+                return Err(syn::Error::new(pattern.span(), err));
+            }
+            Rule::Regex { pattern, overrides }
+        }
+        "custom" => {
+            let mut function = None;
+            meta.parse_nested_meta(|inner| {
+                function = Some(inner.path.clone());
+                Ok(())
+            })?;
+            Rule::Custom(function.ok_or_else(|| meta.error("expected `custom(path::to_fn)`"))?)
+        }
+        "nested" => Rule::Nested,
+        other => {
+            return Err(meta.error(format!("unknown validation rule `{}`", other)));
+        }
+    };
+    Ok(rule)
+}
+
+fn parse_overrides(meta: &ParseNestedMeta) -> syn::Result<Overrides> {
+    let mut overrides = Overrides::default();
+    if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|inner| parse_override(&inner, &mut overrides))?;
+    }
+    Ok(overrides)
+}
+
+fn parse_override(meta: &ParseNestedMeta, overrides: &mut Overrides) -> syn::Result<()> {
+    if meta.path.is_ident("message") {
+        overrides.message = Some(meta.value()?.parse()?);
+    } else if meta.path.is_ident("code") {
+        overrides.code = Some(meta.value()?.parse()?);
+    } else {
+        return Err(meta.error("expected `message` or `code`"));
+    }
+    Ok(())
+}
+
+fn expand_rule(field: &str, rule: &Rule) -> TokenStream2 {
+    let errors = quote!(::cland_rust_share::utils::validation_errors);
+    let private = quote!(#errors::__private);
+    match rule {
+        Rule::Email(o) | Rule::Phone(o) | Rule::Url(o) => {
+            let builtin = match rule {
+                Rule::Email(_) => quote!(#errors::EMAIL),
+                Rule::Phone(_) => quote!(#errors::PHONE),
+                _ => quote!(#errors::URL),
+            };
+            let code = o
+                .code
+                .as_ref()
+                .map_or(quote!(#builtin.code), |c| quote!(#c));
+            let message = o
+                .message
+                .as_ref()
+                .map_or(quote!(#builtin.message), |m| quote!(#m));
+            quote! {
+                if !(#builtin.check)(::std::convert::AsRef::<str>::as_ref(__value)) {
+                    __errors.add(#field, #code, #message);
+                }
+            }
+        }
+        Rule::Length {
+            min,
+            max,
+            overrides,
+        } => {
+            let code = overrides
+                .code
+                .as_ref()
+                .map_or(quote!("length"), |c| quote!(#c));
+            let (ok, default) = bounds(quote!(#private::length(__value)), min, max, "length ");
+            let message = overrides.message.as_ref().map_or(default, |m| quote!(#m));
+            quote! {
+                if !(#ok) {
+                    __errors.add(#field, #code, #message);
+                }
+            }
+        }
+        Rule::Range {
+            min,
+            max,
+            overrides,
+        } => {
+            let code = overrides
+                .code
+                .as_ref()
+                .map_or(quote!("range"), |c| quote!(#c));
+            let (ok, default) = bounds(quote!(*__value), min, max, "");
+            let message = overrides.message.as_ref().map_or(default, |m| quote!(#m));
+            quote! {
+                if !(#ok) {
+                    __errors.add(#field, #code, #message);
+                }
+            }
+        }
+        Rule::Regex { pattern, overrides } => {
+            let code = overrides
+                .code
+                .as_ref()
+                .map_or(quote!("regex"), |c| quote!(#c));
+            let message = overrides
+                .message
+                .as_ref()
+                .map_or(quote!("invalid format"), |m| quote!(#m));
+            quote! {
+                {
+                    static __RE: ::std::sync::LazyLock<#private::Regex> =
+                        ::std::sync::LazyLock::new(|| #private::Regex::new(#pattern).unwrap());
+                    if !__RE.is_match(::std::convert::AsRef::<str>::as_ref(__value)) {
+                        __errors.add(#field, #code, #message);
+                    }
+                }
+            }
+        }
+        Rule::Custom(function) => quote! {
+            if let ::std::result::Result::Err(__message) = #function(__value) {
+                __errors.add(#field, "custom", __message);
+            }
+        },
+        Rule::Nested => quote! {
+            if let ::std::result::Result::Err(__nested) =
+                #errors::Validate::validate(__value)
+            {
+                __errors.nested(#field, __nested);
+            }
+        },
+    }
+}
+
+/// Condition that `value` lies within `min..=max`, and the default message
+fn bounds(
+    value: TokenStream2,
+    min: &Option<Expr>,
+    max: &Option<Expr>,
+    prefix: &str,
+) -> (TokenStream2, TokenStream2) {
+    match (min, max) {
+        (Some(min), Some(max)) => {
+            let text = format!("{}must be between {{}} and {{}}", prefix);
+            (
+                quote!(#value >= #min && #value <= #max),
+                quote!(::std::format!(#text, #min, #max)),
+            )
+        }
+        (Some(min), None) => {
+            let text = format!("{}must be at least {{}}", prefix);
+            (quote!(#value >= #min), quote!(::std::format!(#text, #min)))
+        }
+        (None, Some(max)) => {
+            let text = format!("{}must be at most {{}}", prefix);
+            (quote!(#value <= #max), quote!(::std::format!(#text, #max)))
+        }
+        (None, None) => unreachable!("checked while parsing"),
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "Option"),
+        _ => false,
+    }
+}
@@ -0,0 +1,34 @@
+//! Print the catalog of every registered error code
+//!
+//! ```text
+//! cargo run --example error_catalog -- json
+//! cargo run --example error_catalog -- markdown > docs/error_codes.md
+//! cargo run --example error_catalog -- openapi
+//! ```
+//!
+//! Services register their own code sets on top of `builtin_codes()` and ship
+//! the same few lines as a binary.
+
+use cland_rust_share::error::builtin_codes;
+use cland_rust_share::utils::code_catalog::Catalog;
+
+fn main() {
+    let format = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "json".to_string());
+    let registry = builtin_codes().expect("built-in error codes are unique");
+    let catalog = Catalog::from_registry(&registry);
+
+    match format.as_str() {
+        "json" => println!("{:#}", catalog.to_json()),
+        "markdown" | "md" => print!("{}", catalog.to_markdown()),
+        "openapi" => println!("{:#}", catalog.to_openapi_extension()),
+        other => {
+            eprintln!(
+                "unknown format '{}': expected json, markdown or openapi",
+                other
+            );
+            std::process::exit(2);
+        }
+    }
+}
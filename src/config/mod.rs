@@ -5,6 +5,9 @@
 
 use std::collections::HashMap;
 use std::error::Error as StdError;
+use std::fmt;
+
+use crate::crypto::Secret;
 
 type DynError = Box<dyn StdError + Send + Sync + 'static>;
 
@@ -17,8 +20,12 @@ pub struct Config {
     pub database: DatabaseConfig,
     /// Encryption settings
     pub encryption: EncryptionConfig,
+    /// JWT settings
+    pub jwt: JwtConfig,
     /// Custom key-value pairs
     pub custom: HashMap<String, String>,
+    /// Custom secret values, redacted when printed
+    pub secrets: HashMap<String, Secret<String>>,
 }
 
 /// Application-specific configuration
@@ -38,7 +45,7 @@ pub struct AppConfig {
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
     /// Database connection URL
-    pub url: String,
+    pub url: DatabaseUrl,
     /// Maximum number of connections
     pub max_connections: u32,
     /// Connection timeout in seconds
@@ -63,15 +70,25 @@ impl ConfigBuilder {
                     debug: false,
                 },
                 database: DatabaseConfig {
-                    url: String::new(),
+                    url: DatabaseUrl::default(),
                     max_connections: 10,
                     timeout_seconds: 30,
                 },
                 encryption: EncryptionConfig {
-                    key: String::new(),
+                    key: Secret::default(),
                     algorithm: "aes-256-gcm".to_string(),
+                    keys: Vec::new(),
+                    primary_kid: None,
+                },
+                jwt: JwtConfig {
+                    keys: Vec::new(),
+                    signing_kid: None,
+                    issuer: None,
+                    audience: None,
+                    leeway_seconds: 60,
                 },
                 custom: HashMap::new(),
+                secrets: HashMap::new(),
             },
         }
     }
@@ -102,7 +119,7 @@ impl ConfigBuilder {
 
     /// Set database URL
     pub fn with_database_url(mut self, url: impl Into<String>) -> Self {
-        self.config.database.url = url.into();
+        self.config.database.url = DatabaseUrl::new(url);
         self
     }
 
@@ -120,7 +137,7 @@ impl ConfigBuilder {
 
     /// Set encryption key
     pub fn with_encryption_key(mut self, key: impl Into<String>) -> Self {
-        self.config.encryption.key = key.into();
+        self.config.encryption.key = Secret::new(key.into());
         self
     }
 
@@ -130,12 +147,96 @@ impl ConfigBuilder {
         self
     }
 
+    /// Add a named, base64-encoded encryption key for the keyring
+    pub fn with_encryption_key_entry(
+        mut self,
+        kid: impl Into<String>,
+        key: impl Into<String>,
+    ) -> Self {
+        self.config.encryption.keys.push(EncryptionKey {
+            kid: kid.into(),
+            key: Secret::new(key.into()),
+        });
+        self
+    }
+
+    /// Set the id of the key used for encryption
+    pub fn with_primary_kid(mut self, kid: impl Into<String>) -> Self {
+        self.config.encryption.primary_kid = Some(kid.into());
+        self
+    }
+
+    /// Add a named JWT key; `algorithm` is `HS256` or `EdDSA`, `key` is base64
+    pub fn with_jwt_key(
+        mut self,
+        kid: impl Into<String>,
+        algorithm: impl Into<String>,
+        key: impl Into<String>,
+    ) -> Self {
+        self.config.jwt.keys.push(JwtKeyConfig {
+            kid: kid.into(),
+            algorithm: algorithm.into(),
+            key: Secret::new(key.into()),
+            public: false,
+        });
+        self
+    }
+
+    /// Add a named JWT public key for verification only; `algorithm` must be
+    /// `EdDSA`, `key` is a base64 32-byte Ed25519 public key
+    pub fn with_jwt_public_key(
+        mut self,
+        kid: impl Into<String>,
+        algorithm: impl Into<String>,
+        key: impl Into<String>,
+    ) -> Self {
+        self.config.jwt.keys.push(JwtKeyConfig {
+            kid: kid.into(),
+            algorithm: algorithm.into(),
+            key: Secret::new(key.into()),
+            public: true,
+        });
+        self
+    }
+
+    /// Set the id of the key used to issue tokens
+    pub fn with_jwt_signing_kid(mut self, kid: impl Into<String>) -> Self {
+        self.config.jwt.signing_kid = Some(kid.into());
+        self
+    }
+
+    /// Set the expected token issuer (`iss`)
+    pub fn with_jwt_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.config.jwt.issuer = Some(issuer.into());
+        self
+    }
+
+    /// Set the expected token audience (`aud`)
+    pub fn with_jwt_audience(mut self, audience: impl Into<String>) -> Self {
+        self.config.jwt.audience = Some(audience.into());
+        self
+    }
+
+    /// Set the allowed clock skew for `exp`/`nbf` checks
+    pub fn with_jwt_leeway(mut self, leeway_seconds: u64) -> Self {
+        self.config.jwt.leeway_seconds = leeway_seconds;
+        self
+    }
+
     /// Add custom configuration value
     pub fn with_custom(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.config.custom.insert(key.into(), value.into());
         self
     }
 
+    /// Add a custom secret value
+    pub fn with_secret(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config
+            .secrets
+            .insert(key.into(), Secret::new(value.into()));
+        self
+    }
+
     /// Build the final configuration
     pub fn build(self) -> Config {
         self.config
@@ -152,9 +253,116 @@ impl Default for ConfigBuilder {
 #[derive(Debug, Clone)]
 pub struct EncryptionConfig {
     /// Encryption key
-    pub key: String,
+    pub key: Secret<String>,
     /// Encryption algorithm
     pub algorithm: String,
+    /// Named keys for the keyring, oldest first
+    pub keys: Vec<EncryptionKey>,
+    /// Id of the key used for encryption; defaults to the last entry in `keys`
+    pub primary_kid: Option<String>,
+}
+
+/// Named encryption key entry
+#[derive(Debug, Clone)]
+pub struct EncryptionKey {
+    /// Key id recorded in ciphertext envelopes
+    pub kid: String,
+    /// Base64-encoded 32-byte key
+    pub key: Secret<String>,
+}
+
+/// JWT configuration
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// Signing and verification keys
+    pub keys: Vec<JwtKeyConfig>,
+    /// Id of the key used to issue tokens; defaults to the first key
+    pub signing_kid: Option<String>,
+    /// Expected issuer (`iss`) when validating; `jwt::issue` does not add it,
+    /// set it on the claims with `Claims::with_issuer`
+    pub issuer: Option<String>,
+    /// Expected audience (`aud`) when validating; set it on issued claims with
+    /// `Claims::with_audience`
+    pub audience: Option<String>,
+    /// Allowed clock skew in seconds for `exp` and `nbf`
+    pub leeway_seconds: u64,
+}
+
+/// Named JWT key entry
+#[derive(Debug, Clone)]
+pub struct JwtKeyConfig {
+    /// Key id placed in the token header (`kid`)
+    pub kid: String,
+    /// `HS256` or `EdDSA`
+    pub algorithm: String,
+    /// Base64 HMAC secret, base64 32-byte Ed25519 seed, or base64 32-byte
+    /// Ed25519 public key when `public` is set
+    pub key: Secret<String>,
+    /// Verify-only public key, so services that only check tokens do not
+    /// need the private key
+    pub public: bool,
+}
+
+/// Database connection URL whose credentials are redacted when printed
+///
+/// `postgres://app:s3cret@db:5432/main` prints as `postgres://app:***@db:5432/main`.
+#[derive(Clone, Default)]
+pub struct DatabaseUrl {
+    inner: Secret<String>,
+}
+
+impl DatabaseUrl {
+    /// Wrap a connection URL
+    pub fn new(url: impl Into<String>) -> Self {
+        DatabaseUrl {
+            inner: Secret::new(url.into()),
+        }
+    }
+
+    /// The full URL including credentials
+    pub fn expose_secret(&self) -> &str {
+        self.inner.expose_secret()
+    }
+
+    /// The URL with the password (or a bare credential) replaced by `***`
+    pub fn redacted(&self) -> String {
+        redact_url(self.expose_secret())
+    }
+
+    /// Whether no URL is configured
+    pub fn is_empty(&self) -> bool {
+        self.expose_secret().is_empty()
+    }
+}
+
+impl fmt::Debug for DatabaseUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DatabaseUrl")
+            .field(&self.redacted())
+            .finish()
+    }
+}
+
+impl fmt::Display for DatabaseUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.redacted())
+    }
+}
+
+fn redact_url(url: &str) -> String {
+    let Some(scheme_end) = url.find("://") else {
+        return url.to_string();
+    };
+    let (scheme, rest) = (&url[..scheme_end], &url[scheme_end + 3..]);
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let Some(at) = rest[..authority_end].rfind('@') else {
+        return url.to_string();
+    };
+    let credentials = match rest[..at].split_once(':') {
+        Some((user, _)) => format!("{}:***", user),
+        None => "***".to_string(),
+    };
+    format!("{}://{}@{}", scheme, credentials, &rest[at + 1..])
 }
 
 impl Config {
@@ -191,6 +399,67 @@ impl Config {
             builder = builder.with_encryption_key(enc_key);
         }
 
+        if let Ok(algorithm) = std::env::var("ENCRYPTION_ALGORITHM") {
+            builder = builder.with_encryption_algorithm(algorithm);
+        }
+
+        // ENCRYPTION_KEYS=kid1:base64,kid2:base64
+        if let Ok(keys) = std::env::var("ENCRYPTION_KEYS") {
+            for entry in keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let (kid, key) = entry.split_once(':').ok_or_else(|| {
+                    format!(
+                        "invalid ENCRYPTION_KEYS entry '{}': expected kid:base64",
+                        entry
+                    )
+                })?;
+                builder = builder.with_encryption_key_entry(kid.trim(), key.trim());
+            }
+        }
+
+        if let Ok(kid) = std::env::var("ENCRYPTION_PRIMARY_KID") {
+            builder = builder.with_primary_kid(kid);
+        }
+
+        // JWT_KEYS=kid1:HS256:base64,kid2:EdDSA:base64
+        // JWT_PUBLIC_KEYS=kid3:EdDSA:base64 (verify only)
+        for (var, public) in [("JWT_KEYS", false), ("JWT_PUBLIC_KEYS", true)] {
+            let Ok(keys) = std::env::var(var) else {
+                continue;
+            };
+            for entry in keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let mut parts = entry.splitn(3, ':');
+                let (Some(kid), Some(alg), Some(key)) = (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(format!(
+                        "invalid {} entry '{}': expected kid:algorithm:base64",
+                        var, entry
+                    )
+                    .into());
+                };
+                builder = if public {
+                    builder.with_jwt_public_key(kid.trim(), alg.trim(), key.trim())
+                } else {
+                    builder.with_jwt_key(kid.trim(), alg.trim(), key.trim())
+                };
+            }
+        }
+
+        if let Ok(kid) = std::env::var("JWT_SIGNING_KID") {
+            builder = builder.with_jwt_signing_kid(kid);
+        }
+
+        if let Ok(issuer) = std::env::var("JWT_ISSUER") {
+            builder = builder.with_jwt_issuer(issuer);
+        }
+
+        if let Ok(audience) = std::env::var("JWT_AUDIENCE") {
+            builder = builder.with_jwt_audience(audience);
+        }
+
+        if let Ok(leeway) = std::env::var("JWT_LEEWAY_SECONDS") {
+            builder = builder.with_jwt_leeway(leeway.parse()?);
+        }
+
         Ok(builder.build())
     }
 
@@ -0,0 +1,137 @@
+//! AES-256-GCM authenticated encryption.
+//!
+//! Ciphertexts produced here have the layout `nonce (12 bytes) || ciphertext || tag (16 bytes)`.
+//! A fresh random nonce is generated for every call, so encrypting the same
+//! plaintext twice yields different outputs.
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+
+use super::{CryptoError, OsRandom, RandomSource};
+
+/// AES-256 key length in bytes
+pub const KEY_LEN: usize = 32;
+
+/// AES-GCM nonce length in bytes
+pub const NONCE_LEN: usize = 12;
+
+/// AES-GCM authentication tag length in bytes
+pub const TAG_LEN: usize = 16;
+
+/// AES-256-GCM encryption
+///
+/// `key` may be a 32-byte `&str` or a binary `&[u8; 32]`.
+pub fn aes_encrypt(plaintext: &str, key: impl AsRef<[u8]>) -> Result<Vec<u8>, CryptoError> {
+    aes_encrypt_with_aad(plaintext.as_bytes(), key, &[])
+}
+
+/// AES-256-GCM decryption
+///
+/// Returns [`CryptoError::OperationFailed`] when the key is wrong or the
+/// ciphertext has been tampered with.
+pub fn aes_decrypt(ciphertext: &[u8], key: impl AsRef<[u8]>) -> Result<String, CryptoError> {
+    let plaintext = aes_decrypt_with_aad(ciphertext, key, &[])?;
+    String::from_utf8(plaintext)
+        .map_err(|e| CryptoError::InvalidInput(format!("Invalid UTF-8: {}", e)))
+}
+
+/// AES-256-GCM encryption of binary data, authenticating `aad` alongside it
+///
+/// The same associated data must be supplied to [`aes_decrypt_with_aad`].
+pub fn aes_encrypt_with_aad(
+    plaintext: &[u8],
+    key: impl AsRef<[u8]>,
+    aad: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    aes_encrypt_with_rng(plaintext, key, aad, &mut OsRandom)
+}
+
+/// Same as [`aes_encrypt_with_aad`], drawing the nonce from `rng`
+pub fn aes_encrypt_with_rng(
+    plaintext: &[u8],
+    key: impl AsRef<[u8]>,
+    aad: &[u8],
+    rng: &mut impl RandomSource,
+) -> Result<Vec<u8>, CryptoError> {
+    let cipher = cipher(key.as_ref())?;
+
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_random(&mut nonce)?;
+
+    let sealed = cipher
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| CryptoError::OperationFailed("AES-GCM encryption failed".to_string()))?;
+
+    let mut result = Vec::with_capacity(NONCE_LEN + sealed.len());
+    result.extend_from_slice(&nonce);
+    result.extend_from_slice(&sealed);
+    Ok(result)
+}
+
+/// AES-256-GCM decryption of binary data produced by [`aes_encrypt_with_aad`]
+pub fn aes_decrypt_with_aad(
+    ciphertext: &[u8],
+    key: impl AsRef<[u8]>,
+    aad: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let cipher = cipher(key.as_ref())?;
+
+    if ciphertext.len() < NONCE_LEN + TAG_LEN {
+        return Err(CryptoError::InvalidInput(
+            "Ciphertext too short".to_string(),
+        ));
+    }
+
+    let (nonce, sealed) = ciphertext.split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce.try_into().expect("split at NONCE_LEN");
+    cipher
+        .decrypt(&Nonce::from(nonce), Payload { msg: sealed, aad })
+        .map_err(|_| CryptoError::OperationFailed("AES-GCM authentication failed".to_string()))
+}
+
+fn cipher(key: &[u8]) -> Result<Aes256Gcm, CryptoError> {
+    Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength {
+        expected: KEY_LEN,
+        actual: key.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8; 32] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn output_layout() {
+        let c = aes_encrypt("abc", KEY).unwrap();
+        assert_eq!(c.len(), NONCE_LEN + 3 + TAG_LEN);
+        assert_ne!(aes_encrypt("abc", KEY).unwrap(), c);
+    }
+
+    #[test]
+    fn tampered_tag_is_rejected() {
+        let mut c = aes_encrypt_with_aad(b"payload", KEY, b"ctx").unwrap();
+        let last = c.len() - 1;
+        c[last] ^= 0x01;
+        assert!(matches!(
+            aes_decrypt_with_aad(&c, KEY, b"ctx"),
+            Err(CryptoError::OperationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn aad_must_match() {
+        let c = aes_encrypt_with_aad(b"payload", KEY, b"ctx").unwrap();
+        assert_eq!(aes_decrypt_with_aad(&c, KEY, b"ctx").unwrap(), b"payload");
+        assert!(aes_decrypt_with_aad(&c, KEY, b"other").is_err());
+    }
+}
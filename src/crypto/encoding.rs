@@ -0,0 +1,317 @@
+//! Text encodings for keys, ciphertexts and digests.
+//!
+//! Base64 in standard and URL-safe alphabets (padded or not), lowercase and
+//! uppercase hex, RFC 4648 base32 and RFC 3986 percent-encoding. Decoders
+//! return [`EncodingError`] with the offending position. [`EncodeExt`] adds
+//! `.to_base64()` / `.to_hex()` to any byte output, e.g.
+//! `crypto::sha256_hash(data).to_hex()`.
+
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+};
+use data_encoding::{BASE32, BASE32_NOPAD, DecodeKind, HEXLOWER, HEXLOWER_PERMISSIVE, HEXUPPER};
+
+use super::{CryptoError, constant_time_eq};
+
+/// Decoding failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum EncodingError {
+    /// Input length is not possible for this encoding
+    #[error("invalid {encoding} length: {len}")]
+    InvalidLength { encoding: &'static str, len: usize },
+
+    /// Character outside the alphabet, or with non-zero trailing bits
+    #[error("invalid {encoding} symbol at offset {offset}")]
+    InvalidSymbol {
+        encoding: &'static str,
+        offset: usize,
+    },
+
+    /// Padding is missing, misplaced or not allowed
+    #[error("invalid {encoding} padding")]
+    InvalidPadding { encoding: &'static str },
+}
+
+impl From<EncodingError> for CryptoError {
+    fn from(err: EncodingError) -> Self {
+        CryptoError::InvalidInput(err.to_string())
+    }
+}
+
+/// Base64 alphabet and padding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base64 {
+    /// RFC 4648 section 4, padded
+    Standard,
+    /// RFC 4648 section 4, unpadded
+    StandardNoPad,
+    /// RFC 4648 section 5 (`-` and `_`), padded
+    UrlSafe,
+    /// RFC 4648 section 5 (`-` and `_`), unpadded, as used in JWTs and URLs
+    UrlSafeNoPad,
+}
+
+impl Base64 {
+    /// Encode `data`
+    pub fn encode(self, data: impl AsRef<[u8]>) -> String {
+        match self {
+            Base64::Standard => STANDARD.encode(data),
+            Base64::StandardNoPad => STANDARD_NO_PAD.encode(data),
+            Base64::UrlSafe => URL_SAFE.encode(data),
+            Base64::UrlSafeNoPad => URL_SAFE_NO_PAD.encode(data),
+        }
+    }
+
+    /// Decode `input`, which must use exactly this alphabet and padding
+    pub fn decode(self, input: impl AsRef<[u8]>) -> Result<Vec<u8>, EncodingError> {
+        let result = match self {
+            Base64::Standard => STANDARD.decode(input),
+            Base64::StandardNoPad => STANDARD_NO_PAD.decode(input),
+            Base64::UrlSafe => URL_SAFE.decode(input),
+            Base64::UrlSafeNoPad => URL_SAFE_NO_PAD.decode(input),
+        };
+        result.map_err(|e| {
+            let encoding = self.name();
+            match e {
+                base64::DecodeError::InvalidByte(offset, _)
+                | base64::DecodeError::InvalidLastSymbol(offset, _) => {
+                    EncodingError::InvalidSymbol { encoding, offset }
+                }
+                base64::DecodeError::InvalidLength(len) => {
+                    EncodingError::InvalidLength { encoding, len }
+                }
+                base64::DecodeError::InvalidPadding => EncodingError::InvalidPadding { encoding },
+            }
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Base64::Standard | Base64::StandardNoPad => "base64",
+            Base64::UrlSafe | Base64::UrlSafeNoPad => "base64url",
+        }
+    }
+}
+
+/// Standard padded base64
+pub fn base64_encode(data: impl AsRef<[u8]>) -> String {
+    Base64::Standard.encode(data)
+}
+
+/// Decode standard padded base64
+pub fn base64_decode(input: impl AsRef<[u8]>) -> Result<Vec<u8>, EncodingError> {
+    Base64::Standard.decode(input)
+}
+
+/// Unpadded URL-safe base64
+pub fn base64url_encode(data: impl AsRef<[u8]>) -> String {
+    Base64::UrlSafeNoPad.encode(data)
+}
+
+/// Decode unpadded URL-safe base64
+pub fn base64url_decode(input: impl AsRef<[u8]>) -> Result<Vec<u8>, EncodingError> {
+    Base64::UrlSafeNoPad.decode(input)
+}
+
+/// Lowercase hex
+pub fn hex_encode(data: impl AsRef<[u8]>) -> String {
+    HEXLOWER.encode(data.as_ref())
+}
+
+/// Uppercase hex
+pub fn hex_encode_upper(data: impl AsRef<[u8]>) -> String {
+    HEXUPPER.encode(data.as_ref())
+}
+
+/// Decode hex in either case
+pub fn hex_decode(input: impl AsRef<[u8]>) -> Result<Vec<u8>, EncodingError> {
+    let input = input.as_ref();
+    HEXLOWER_PERMISSIVE
+        .decode(input)
+        .map_err(|e| map_data_encoding("hex", input.len(), e))
+}
+
+/// Compare two hex strings (e.g. MACs) in constant time, ignoring case
+///
+/// Returns `false` if either side is not valid hex.
+pub fn hex_eq(a: &str, b: &str) -> bool {
+    match (hex_decode(a), hex_decode(b)) {
+        (Ok(a), Ok(b)) => constant_time_eq(&a, &b),
+        _ => false,
+    }
+}
+
+/// RFC 4648 base32, padded
+pub fn base32_encode(data: impl AsRef<[u8]>) -> String {
+    BASE32.encode(data.as_ref())
+}
+
+/// RFC 4648 base32 without padding, as used in `otpauth://` secrets
+pub fn base32_encode_no_pad(data: impl AsRef<[u8]>) -> String {
+    BASE32_NOPAD.encode(data.as_ref())
+}
+
+/// Decode RFC 4648 base32
+///
+/// Accepts lowercase letters, missing padding and spaces, since base32 is
+/// often typed in by hand. Error offsets and lengths refer to `input` as
+/// given, spaces included.
+pub fn base32_decode(input: &str) -> Result<Vec<u8>, EncodingError> {
+    // byte offset in `input` of every byte kept in `normalized`
+    let mut offsets = Vec::with_capacity(input.len());
+    let mut normalized = String::with_capacity(input.len());
+    for (i, c) in input.char_indices().filter(|(_, c)| *c != ' ') {
+        normalized.push(c.to_ascii_uppercase());
+        offsets.extend(i..i + c.len_utf8());
+    }
+    let trimmed = normalized.trim_end_matches('=');
+    BASE32_NOPAD.decode(trimmed.as_bytes()).map_err(|mut e| {
+        e.position = offsets.get(e.position).copied().unwrap_or(input.len());
+        map_data_encoding("base32", input.len(), e)
+    })
+}
+
+/// RFC 3986 percent-encoding of everything except unreserved characters
+pub fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// `len` is the length of the input that was decoded; `e.position` is an offset
+fn map_data_encoding(
+    encoding: &'static str,
+    len: usize,
+    e: data_encoding::DecodeError,
+) -> EncodingError {
+    match e.kind {
+        DecodeKind::Length => EncodingError::InvalidLength { encoding, len },
+        DecodeKind::Padding => EncodingError::InvalidPadding { encoding },
+        DecodeKind::Symbol | DecodeKind::Trailing => EncodingError::InvalidSymbol {
+            encoding,
+            offset: e.position,
+        },
+    }
+}
+
+/// `.to_base64()` and `.to_hex()` on byte outputs such as ciphertexts and digests
+pub trait EncodeExt {
+    /// Standard padded base64
+    fn to_base64(&self) -> String;
+
+    /// Unpadded URL-safe base64
+    fn to_base64url(&self) -> String;
+
+    /// Lowercase hex
+    fn to_hex(&self) -> String;
+}
+
+impl<T: AsRef<[u8]> + ?Sized> EncodeExt for T {
+    fn to_base64(&self) -> String {
+        base64_encode(self.as_ref())
+    }
+
+    fn to_base64url(&self) -> String {
+        base64url_encode(self.as_ref())
+    }
+
+    fn to_hex(&self) -> String {
+        hex_encode(self.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc4648_vectors() {
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(Base64::StandardNoPad.encode(b"foob"), "Zm9vYg");
+        assert_eq!(base64url_encode([0xfb, 0xff]), "-_8");
+        assert_eq!(Base64::UrlSafe.encode([0xfb, 0xff]), "-_8=");
+        assert_eq!(base32_encode(b"foob"), "MZXW6YQ=");
+        assert_eq!(base32_encode_no_pad(b"foob"), "MZXW6YQ");
+        assert_eq!(hex_encode(b"\x01\xab"), "01ab");
+        assert_eq!(hex_encode_upper(b"\x01\xab"), "01AB");
+    }
+
+    #[test]
+    fn round_trips() {
+        let data: Vec<u8> = (0..=255).collect();
+        for variant in [
+            Base64::Standard,
+            Base64::StandardNoPad,
+            Base64::UrlSafe,
+            Base64::UrlSafeNoPad,
+        ] {
+            assert_eq!(variant.decode(variant.encode(&data)).unwrap(), data);
+        }
+        assert_eq!(hex_decode(hex_encode_upper(&data)).unwrap(), data);
+        assert_eq!(base32_decode(&base32_encode(&data)).unwrap(), data);
+        assert_eq!(base32_decode("mzxw 6yq").unwrap(), b"foob");
+    }
+
+    #[test]
+    fn typed_errors() {
+        assert_eq!(
+            hex_decode("abc"),
+            Err(EncodingError::InvalidLength {
+                encoding: "hex",
+                len: 3
+            })
+        );
+        assert_eq!(
+            base32_decode("MZX"),
+            Err(EncodingError::InvalidLength {
+                encoding: "base32",
+                len: 3
+            })
+        );
+        assert_eq!(
+            hex_decode("zz"),
+            Err(EncodingError::InvalidSymbol {
+                encoding: "hex",
+                offset: 0
+            })
+        );
+        assert_eq!(
+            base32_decode("ABCD EFG!"),
+            Err(EncodingError::InvalidSymbol {
+                encoding: "base32",
+                offset: 8
+            })
+        );
+        assert_eq!(
+            base32_decode("MZ X"),
+            Err(EncodingError::InvalidLength {
+                encoding: "base32",
+                len: 4
+            })
+        );
+        assert!(matches!(
+            base64url_decode("a+b/"),
+            Err(EncodingError::InvalidSymbol { offset: 1, .. })
+        ));
+        assert_eq!(
+            base64_decode("Zm9vYg"),
+            Err(EncodingError::InvalidPadding { encoding: "base64" })
+        );
+    }
+
+    #[test]
+    fn hex_comparison() {
+        assert!(hex_eq("01ab", "01AB"));
+        assert!(!hex_eq("01ab", "01ac"));
+        assert!(!hex_eq("01ab", "01a"));
+        assert_eq!(vec![0xde, 0xad].to_hex(), "dead");
+        assert_eq!([0xfb, 0xff].to_base64url(), "-_8");
+    }
+}
@@ -0,0 +1,383 @@
+//! Self-describing ciphertext envelope.
+//!
+//! Binary layout (version 1):
+//!
+//! ```text
+//! version:u8 | algorithm:u8 | kid_len:u8 | kid | nonce (12) | ciphertext | tag (16)
+//! ```
+//!
+//! The header (version, algorithm and key id) is authenticated as associated
+//! data, so it cannot be altered without [`open`] failing. The text form is
+//! the standard base64 encoding of the binary layout.
+
+use std::fmt;
+use std::str::FromStr;
+
+use aes_gcm::{
+    Aes256Gcm,
+    aead::{Aead, KeyInit, Payload},
+};
+use chacha20poly1305::ChaCha20Poly1305;
+
+use super::{
+    CryptoError, Keyring, OsRandom, RandomSource,
+    aes::{KEY_LEN, NONCE_LEN, TAG_LEN},
+    encoding::{base64_decode, base64_encode},
+};
+
+/// Current envelope format version
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Authenticated encryption algorithm recorded in an envelope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Algorithm {
+    /// AES-256-GCM
+    #[default]
+    Aes256Gcm,
+    /// ChaCha20-Poly1305
+    ChaCha20Poly1305,
+}
+
+impl Algorithm {
+    /// Name as used by `EncryptionConfig::algorithm`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Algorithm::Aes256Gcm => "aes-256-gcm",
+            Algorithm::ChaCha20Poly1305 => "chacha20-poly1305",
+        }
+    }
+
+    /// Identifier stored in the envelope header
+    pub fn id(self) -> u8 {
+        match self {
+            Algorithm::Aes256Gcm => 1,
+            Algorithm::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    /// Look up an algorithm by its header identifier
+    pub fn from_id(id: u8) -> Result<Self, CryptoError> {
+        match id {
+            1 => Ok(Algorithm::Aes256Gcm),
+            2 => Ok(Algorithm::ChaCha20Poly1305),
+            other => Err(CryptoError::UnsupportedAlgorithm(format!("id {}", other))),
+        }
+    }
+
+    /// Encrypt `msg`, returning `ciphertext || tag`
+    pub(crate) fn encrypt(
+        self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        msg: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let payload = Payload { msg, aad };
+        let result = match self {
+            Algorithm::Aes256Gcm => {
+                Aes256Gcm::new(&(*key).into()).encrypt(&(*nonce).into(), payload)
+            }
+            Algorithm::ChaCha20Poly1305 => {
+                ChaCha20Poly1305::new(&(*key).into()).encrypt(&(*nonce).into(), payload)
+            }
+        };
+        result.map_err(|_| CryptoError::OperationFailed(format!("{} encryption failed", self)))
+    }
+
+    /// Decrypt `ciphertext || tag`
+    pub(crate) fn decrypt(
+        self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        msg: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let payload = Payload { msg, aad };
+        let result = match self {
+            Algorithm::Aes256Gcm => {
+                Aes256Gcm::new(&(*key).into()).decrypt(&(*nonce).into(), payload)
+            }
+            Algorithm::ChaCha20Poly1305 => {
+                ChaCha20Poly1305::new(&(*key).into()).decrypt(&(*nonce).into(), payload)
+            }
+        };
+        result.map_err(|_| CryptoError::OperationFailed(format!("{} authentication failed", self)))
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "aes-256-gcm" => Ok(Algorithm::Aes256Gcm),
+            "chacha20-poly1305" => Ok(Algorithm::ChaCha20Poly1305),
+            _ => Err(CryptoError::UnsupportedAlgorithm(s.to_string())),
+        }
+    }
+}
+
+/// Encrypted payload together with everything needed to decrypt it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    version: u8,
+    algorithm: Algorithm,
+    key_id: String,
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+    tag: [u8; TAG_LEN],
+}
+
+impl Envelope {
+    /// Format version
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Algorithm used to encrypt the payload
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Id of the key used to encrypt the payload
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Nonce used to encrypt the payload
+    pub fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+
+    /// Encrypted payload without the tag
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Authentication tag
+    pub fn tag(&self) -> &[u8] {
+        &self.tag
+    }
+
+    /// Serialize to the binary layout
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = header(self.algorithm, &self.key_id);
+        out.reserve(NONCE_LEN + self.ciphertext.len() + TAG_LEN);
+        out.extend_from_slice(&self.nonce);
+        out.extend_from_slice(&self.ciphertext);
+        out.extend_from_slice(&self.tag);
+        out
+    }
+
+    /// Parse the binary layout
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        let (&version, rest) = bytes
+            .split_first()
+            .ok_or_else(|| CryptoError::InvalidInput("Empty envelope".to_string()))?;
+        if version != ENVELOPE_VERSION {
+            return Err(CryptoError::UnsupportedVersion(version));
+        }
+
+        let [alg, kid_len, rest @ ..] = rest else {
+            return Err(CryptoError::InvalidInput(
+                "Envelope header truncated".to_string(),
+            ));
+        };
+        let algorithm = Algorithm::from_id(*alg)?;
+        let kid_len = *kid_len as usize;
+
+        if rest.len() < kid_len + NONCE_LEN + TAG_LEN {
+            return Err(CryptoError::InvalidInput("Envelope too short".to_string()));
+        }
+        let (kid, rest) = rest.split_at(kid_len);
+        let key_id = std::str::from_utf8(kid)
+            .map_err(|e| CryptoError::InvalidInput(format!("Invalid key id: {}", e)))?
+            .to_string();
+        let (nonce, rest) = rest.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+        Ok(Envelope {
+            version,
+            algorithm,
+            key_id,
+            nonce: nonce.try_into().expect("split at NONCE_LEN"),
+            ciphertext: ciphertext.to_vec(),
+            tag: tag.try_into().expect("split at TAG_LEN"),
+        })
+    }
+
+    /// Serialize to standard base64
+    pub fn to_base64(&self) -> String {
+        base64_encode(self.to_bytes())
+    }
+
+    /// Parse from standard base64
+    pub fn from_base64(s: &str) -> Result<Self, CryptoError> {
+        let bytes = base64_decode(s.trim())?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl fmt::Display for Envelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_base64())
+    }
+}
+
+impl FromStr for Envelope {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_base64(s)
+    }
+}
+
+/// Encrypt `plaintext` with the keyring's primary key
+pub fn seal(keyring: &Keyring, plaintext: &[u8]) -> Result<Envelope, CryptoError> {
+    seal_with_aad(keyring, plaintext, &[])
+}
+
+/// Encrypt `plaintext` with the keyring's primary key, also authenticating `aad`
+pub fn seal_with_aad(
+    keyring: &Keyring,
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Envelope, CryptoError> {
+    seal_with_rng(keyring, plaintext, aad, &mut OsRandom)
+}
+
+/// Same as [`seal_with_aad`], drawing the nonce from `rng`
+pub fn seal_with_rng(
+    keyring: &Keyring,
+    plaintext: &[u8],
+    aad: &[u8],
+    rng: &mut impl RandomSource,
+) -> Result<Envelope, CryptoError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_random(&mut nonce)?;
+    seal_with_nonce(keyring, plaintext, aad, nonce)
+}
+
+/// Seal with a caller-chosen nonce; only for synthetic (deterministic) nonces
+pub(crate) fn seal_with_nonce(
+    keyring: &Keyring,
+    plaintext: &[u8],
+    aad: &[u8],
+    nonce: [u8; NONCE_LEN],
+) -> Result<Envelope, CryptoError> {
+    let (kid, key) = keyring.primary();
+    let algorithm = keyring.algorithm();
+
+    let mut sealed = algorithm.encrypt(key, &nonce, plaintext, &full_aad(algorithm, kid, aad))?;
+    let tag = sealed.split_off(sealed.len() - TAG_LEN);
+
+    Ok(Envelope {
+        version: ENVELOPE_VERSION,
+        algorithm,
+        key_id: kid.to_string(),
+        nonce,
+        ciphertext: sealed,
+        tag: tag.try_into().expect("split at TAG_LEN"),
+    })
+}
+
+/// Decrypt an envelope using the key it names
+pub fn open(keyring: &Keyring, envelope: &Envelope) -> Result<Vec<u8>, CryptoError> {
+    open_with_aad(keyring, envelope, &[])
+}
+
+/// Decrypt an envelope sealed with [`seal_with_aad`]
+pub fn open_with_aad(
+    keyring: &Keyring,
+    envelope: &Envelope,
+    aad: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    if envelope.version != ENVELOPE_VERSION {
+        return Err(CryptoError::UnsupportedVersion(envelope.version));
+    }
+    let key = keyring.require(&envelope.key_id)?;
+
+    let mut sealed = Vec::with_capacity(envelope.ciphertext.len() + TAG_LEN);
+    sealed.extend_from_slice(&envelope.ciphertext);
+    sealed.extend_from_slice(&envelope.tag);
+
+    envelope.algorithm.decrypt(
+        key,
+        &envelope.nonce,
+        &sealed,
+        &full_aad(envelope.algorithm, &envelope.key_id, aad),
+    )
+}
+
+fn header(algorithm: Algorithm, kid: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(3 + kid.len());
+    out.push(ENVELOPE_VERSION);
+    out.push(algorithm.id());
+    out.push(kid.len() as u8);
+    out.extend_from_slice(kid.as_bytes());
+    out
+}
+
+fn full_aad(algorithm: Algorithm, kid: &str, aad: &[u8]) -> Vec<u8> {
+    let mut out = header(algorithm, kid);
+    out.extend_from_slice(aad);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring() -> Keyring {
+        Keyring::new("k1", [1u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let env = seal(&keyring(), b"hello").unwrap();
+        let parsed = Envelope::from_bytes(&env.to_bytes()).unwrap();
+        assert_eq!(parsed, env);
+        assert_eq!(parsed.key_id(), "k1");
+        assert_eq!(parsed.ciphertext().len(), 5);
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let mut bytes = seal(&keyring(), b"hello").unwrap().to_bytes();
+        bytes[0] = 9;
+        assert!(matches!(
+            Envelope::from_bytes(&bytes),
+            Err(CryptoError::UnsupportedVersion(9))
+        ));
+    }
+
+    #[test]
+    fn header_is_authenticated() {
+        let mut ring = keyring();
+        ring.add_key("k2", [1u8; 32]).unwrap();
+        let env = seal(&ring, b"hello").unwrap();
+
+        // Same key material under another id must not verify
+        let mut forged = env.clone();
+        forged.key_id = "k2".to_string();
+        assert!(matches!(
+            open(&ring, &forged),
+            Err(CryptoError::OperationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn algorithm_names() {
+        for alg in [Algorithm::Aes256Gcm, Algorithm::ChaCha20Poly1305] {
+            assert_eq!(alg.as_str().parse::<Algorithm>().unwrap(), alg);
+            assert_eq!(Algorithm::from_id(alg.id()).unwrap(), alg);
+        }
+        assert!("des".parse::<Algorithm>().is_err());
+    }
+}
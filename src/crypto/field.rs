@@ -0,0 +1,308 @@
+//! Field-level encryption for PII columns.
+//!
+//! [`EncryptedField<T>`] serializes to a base64 [`Envelope`](super::Envelope)
+//! string and back, using the keyring installed with [`install_keyring`].
+//! Values are first serialized as JSON, so any `T: Serialize + Deserialize`
+//! works.
+//!
+//! Two modes are available:
+//!
+//! - [`Randomized`] (the default) seals with a random nonce; equal values give
+//!   different ciphertexts.
+//! - [`Deterministic`] derives the nonce from an HMAC of the value (a synthetic
+//!   IV, as in SIV), so equal values give equal ciphertexts under the same
+//!   primary key and the column can be queried with `=`. This reveals which
+//!   rows share a value; use it only for fields that must be searchable.
+//!
+//! Both modes use their own subkeys derived from the keyring, never the master
+//! keys. Deterministic ciphertexts change when the primary key rotates, so
+//! rows sealed under an older key need [`EncryptedField::reseal`] before they
+//! match again. Alternatively keep the column [`Randomized`] and store a
+//! [`blind_index`] next to it for lookups.
+//!
+//! ```
+//! use cland_rust_share::crypto::{Keyring, field::{self, EncryptedField, Searchable}};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     phone: Searchable<String>,
+//!     id_card: EncryptedField<String>,
+//! }
+//!
+//! field::install_keyring(&Keyring::new("k1", [7u8; 32]).unwrap()).unwrap();
+//! let user = User {
+//!     phone: Searchable::new("13800000000".to_string()),
+//!     id_card: EncryptedField::new("110101199001011234".to_string()),
+//! };
+//! let json = serde_json::to_string(&user).unwrap();
+//! assert!(!json.contains("13800000000"));
+//!
+//! let back: User = serde_json::from_str(&json).unwrap();
+//! assert_eq!(*back.phone, "13800000000");
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, RwLock};
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::aes::NONCE_LEN;
+use super::encoding::hex_encode;
+use super::envelope::seal_with_nonce;
+use super::{CryptoError, Envelope, Keyring, Secret, hmac_sha256, open_with_aad, seal_with_aad};
+use crate::config::Config;
+
+/// Purpose labels for the subkeys derived from the installed keyring
+const RANDOMIZED_PURPOSE: &str = "field/randomized";
+const DETERMINISTIC_PURPOSE: &str = "field/deterministic";
+const SIV_PURPOSE: &str = "field/siv";
+const BLIND_INDEX_PURPOSE: &str = "field/blind-index";
+
+static FIELD_KEYS: RwLock<Option<Arc<FieldKeys>>> = RwLock::new(None);
+
+struct FieldKeys {
+    randomized: Keyring,
+    deterministic: Keyring,
+    siv: Secret<[u8; 32]>,
+    blind_index: Secret<[u8; 32]>,
+}
+
+/// Install the keyring used by every [`EncryptedField`]
+///
+/// Replaces any previously installed keyring, e.g. after a rotation. Retired
+/// keys in `keyring` keep decrypting values sealed before the rotation.
+pub fn install_keyring(keyring: &Keyring) -> Result<(), CryptoError> {
+    let keys = FieldKeys {
+        randomized: keyring.derive(RANDOMIZED_PURPOSE)?,
+        deterministic: keyring.derive(DETERMINISTIC_PURPOSE)?,
+        siv: keyring.derive_key(SIV_PURPOSE)?,
+        blind_index: keyring.derive_key(BLIND_INDEX_PURPOSE)?,
+    };
+    *FIELD_KEYS.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(keys));
+    Ok(())
+}
+
+/// Install the keyring described by `EncryptionConfig`; see [`Keyring::from_config`]
+pub fn install_keyring_from_config(config: &Config) -> Result<(), CryptoError> {
+    install_keyring(&Keyring::from_config(config)?)
+}
+
+/// Whether a keyring has been installed
+pub fn is_installed() -> bool {
+    FIELD_KEYS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some()
+}
+
+fn installed_keys() -> Result<Arc<FieldKeys>, CryptoError> {
+    FIELD_KEYS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| CryptoError::InvalidInput("no field keyring installed".to_string()))
+}
+
+/// Keyed hash of `value` for equality lookups, as lowercase hex
+///
+/// Uses its own subkey of the installed primary key, so equal values always
+/// give equal indexes until that key is rotated.
+pub fn blind_index<T: Serialize + ?Sized>(value: &T) -> Result<String, CryptoError> {
+    let keys = installed_keys()?;
+    let plaintext = to_plaintext(value)?;
+    Ok(hex_encode(hmac_sha256(
+        keys.blind_index.expose_secret(),
+        &plaintext,
+    )))
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Encryption mode of an [`EncryptedField`]; implemented by [`Randomized`] and [`Deterministic`]
+pub trait FieldMode: sealed::Sealed {
+    #[doc(hidden)]
+    const DETERMINISTIC: bool;
+    #[doc(hidden)]
+    const AAD: &'static [u8];
+}
+
+/// Random nonce per value; equal values give different ciphertexts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Randomized;
+
+/// Synthetic nonce per value; equal values give equal ciphertexts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Deterministic;
+
+impl sealed::Sealed for Randomized {}
+impl sealed::Sealed for Deterministic {}
+
+impl FieldMode for Randomized {
+    const DETERMINISTIC: bool = false;
+    const AAD: &'static [u8] = b"cland-rust-share/field/randomized";
+}
+
+impl FieldMode for Deterministic {
+    const DETERMINISTIC: bool = true;
+    const AAD: &'static [u8] = b"cland-rust-share/field/deterministic";
+}
+
+/// Deterministically encrypted field that supports equality lookups
+pub type Searchable<T> = EncryptedField<T, Deterministic>;
+
+/// A value encrypted when serialized and decrypted when deserialized
+///
+/// In memory it holds the plaintext, reachable through `Deref`; `Debug` never
+/// prints it.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct EncryptedField<T, M: FieldMode = Randomized> {
+    value: T,
+    mode: PhantomData<M>,
+}
+
+impl<T, M: FieldMode> EncryptedField<T, M> {
+    /// Wrap a plaintext value
+    pub fn new(value: T) -> Self {
+        EncryptedField {
+            value,
+            mode: PhantomData,
+        }
+    }
+
+    /// Plaintext value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize, M: FieldMode> EncryptedField<T, M> {
+    /// Ciphertext as stored, e.g. to build a `WHERE phone = ?` query for a [`Searchable`] field
+    pub fn ciphertext(&self) -> Result<String, CryptoError> {
+        let keys = installed_keys()?;
+        let plaintext = to_plaintext(&self.value)?;
+        let envelope = if M::DETERMINISTIC {
+            let mac = hmac_sha256(keys.siv.expose_secret(), &[M::AAD, &plaintext].concat());
+            let nonce: [u8; NONCE_LEN] =
+                mac[..NONCE_LEN].try_into().expect("HMAC output is longer");
+            seal_with_nonce(&keys.deterministic, &plaintext, M::AAD, nonce)?
+        } else {
+            seal_with_aad(&keys.randomized, &plaintext, M::AAD)?
+        };
+        Ok(envelope.to_base64())
+    }
+
+    /// Keyed hash of the value; see [`blind_index`]
+    pub fn blind_index(&self) -> Result<String, CryptoError> {
+        blind_index(&self.value)
+    }
+}
+
+impl<T: DeserializeOwned, M: FieldMode> EncryptedField<T, M> {
+    /// Decrypt a stored ciphertext
+    pub fn from_ciphertext(ciphertext: &str) -> Result<Self, CryptoError> {
+        let keys = installed_keys()?;
+        let envelope = Envelope::from_base64(ciphertext)?;
+        let keyring = if M::DETERMINISTIC {
+            &keys.deterministic
+        } else {
+            &keys.randomized
+        };
+        let plaintext = Secret::new(open_with_aad(keyring, &envelope, M::AAD)?);
+        serde_json::from_slice(plaintext.expose_secret())
+            .map(Self::new)
+            .map_err(|e| CryptoError::InvalidInput(format!("invalid field value: {}", e)))
+    }
+}
+
+impl<T: Serialize + DeserializeOwned, M: FieldMode> EncryptedField<T, M> {
+    /// Decrypt `ciphertext` and seal it again under the current primary key
+    pub fn reseal(ciphertext: &str) -> Result<String, CryptoError> {
+        Self::from_ciphertext(ciphertext)?.ciphertext()
+    }
+}
+
+impl<T, M: FieldMode> From<T> for EncryptedField<T, M> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T, M: FieldMode> Deref for EncryptedField<T, M> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, M: FieldMode> DerefMut for EncryptedField<T, M> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T, M: FieldMode> fmt::Debug for EncryptedField<T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptedField(***)")
+    }
+}
+
+impl<T: Serialize, M: FieldMode> Serialize for EncryptedField<T, M> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ciphertext = self.ciphertext().map_err(S::Error::custom)?;
+        serializer.serialize_str(&ciphertext)
+    }
+}
+
+impl<'de, T: DeserializeOwned, M: FieldMode> Deserialize<'de> for EncryptedField<T, M> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ciphertext = String::deserialize(deserializer)?;
+        Self::from_ciphertext(&ciphertext).map_err(D::Error::custom)
+    }
+}
+
+fn to_plaintext<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, CryptoError> {
+    serde_json::to_vec(value)
+        .map_err(|e| CryptoError::InvalidInput(format!("cannot serialize field value: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install() {
+        install_keyring(&Keyring::new("k1", [7u8; 32]).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn modes() {
+        install();
+        let a = Searchable::new(42u32).ciphertext().unwrap();
+        let b = Searchable::new(42u32).ciphertext().unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, Searchable::new(43u32).ciphertext().unwrap());
+
+        let c = EncryptedField::<_>::new(42u32).ciphertext().unwrap();
+        let d = EncryptedField::<_>::new(42u32).ciphertext().unwrap();
+        assert_ne!(c, d);
+
+        assert_eq!(*Searchable::<u32>::from_ciphertext(&a).unwrap(), 42);
+        assert_eq!(*EncryptedField::<u32>::from_ciphertext(&c).unwrap(), 42);
+        // A ciphertext from one mode never opens in the other
+        assert!(EncryptedField::<u32>::from_ciphertext(&a).is_err());
+        assert!(Searchable::<u32>::from_ciphertext(&c).is_err());
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let field = EncryptedField::<_>::new("13800000000".to_string());
+        assert_eq!(format!("{:?}", field), "EncryptedField(***)");
+    }
+}
@@ -0,0 +1,561 @@
+//! JSON Web Tokens (RFC 7519) signed with HS256 or EdDSA (Ed25519).
+//!
+//! Keys are looked up by the `kid` header, and each key is bound to a single
+//! algorithm, so a token cannot choose a weaker algorithm than its key allows.
+//! Validation failures are reported as [`CommonError::TokenExpired`],
+//! [`CommonError::TokenNotYetValid`], [`CommonError::InvalidToken`] or
+//! [`CommonError::InvalidClaim`], which map to `401` structured codes via
+//! [`CommonError::code`].
+//!
+//! ```
+//! use cland_rust_share::crypto::jwt::{self, Claims, JwtKeys, Validation};
+//!
+//! let keys = JwtKeys::new().with_hs256("k1", b"0123456789abcdef0123456789abcdef");
+//! let token = jwt::issue(&keys, &Claims::new(()).with_subject("user-1").expires_in(600))?;
+//!
+//! let claims: Claims<()> = jwt::verify(&keys, &token, &Validation::default())?;
+//! assert_eq!(claims.sub.as_deref(), Some("user-1"));
+//! # Ok::<(), cland_rust_share::CommonError>(())
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+
+use super::Secret;
+use super::encoding::{base64_decode, base64url_decode, base64url_encode};
+use super::hmac::{hmac_sha256, verify_hmac_sha256};
+use super::sign::{KeyPair, PublicKey};
+use crate::{CommonError, config::Config, utils::current_timestamp};
+
+/// Minimum HS256 secret length in bytes
+pub const MIN_HS256_SECRET_LEN: usize = 32;
+
+/// Supported signing algorithms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum JwtAlgorithm {
+    /// HMAC-SHA256
+    HS256,
+    /// Ed25519
+    EdDSA,
+}
+
+impl std::str::FromStr for JwtAlgorithm {
+    type Err = CommonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "HS256" => Ok(JwtAlgorithm::HS256),
+            "EdDSA" => Ok(JwtAlgorithm::EdDSA),
+            other => Err(CommonError::config(format!(
+                "unsupported JWT algorithm '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Key material for one `kid`
+#[derive(Clone)]
+pub enum JwtKey {
+    /// HS256 shared secret; signs and verifies
+    Hs256(Secret<Vec<u8>>),
+    /// Ed25519 private key; signs and verifies
+    Ed25519Signing(KeyPair),
+    /// Ed25519 public key; verifies only
+    Ed25519Verifying(PublicKey),
+}
+
+impl JwtKey {
+    /// Algorithm this key is bound to
+    pub fn algorithm(&self) -> JwtAlgorithm {
+        match self {
+            JwtKey::Hs256(_) => JwtAlgorithm::HS256,
+            JwtKey::Ed25519Signing(_) | JwtKey::Ed25519Verifying(_) => JwtAlgorithm::EdDSA,
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CommonError> {
+        match self {
+            JwtKey::Hs256(secret) => Ok(hmac_sha256(secret.expose_secret(), message)),
+            JwtKey::Ed25519Signing(key) => Ok(key.sign(message).to_vec()),
+            JwtKey::Ed25519Verifying(_) => Err(CommonError::config(
+                "JWT signing key is a public key and cannot sign",
+            )),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        let public = match self {
+            JwtKey::Hs256(secret) => {
+                return verify_hmac_sha256(secret.expose_secret(), message, signature);
+            }
+            JwtKey::Ed25519Signing(key) => key.public_key(),
+            JwtKey::Ed25519Verifying(key) => *key,
+        };
+        public.verify(message, signature).is_ok()
+    }
+}
+
+impl fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print key material
+        write!(f, "JwtKey({:?})", self.algorithm())
+    }
+}
+
+/// Keys available for issuing and verifying tokens, by `kid`
+#[derive(Debug, Clone, Default)]
+pub struct JwtKeys {
+    keys: HashMap<String, JwtKey>,
+    signing_kid: Option<String>,
+}
+
+impl JwtKeys {
+    /// Empty key set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load keys from `Config::jwt`
+    pub fn from_config(config: &Config) -> Result<Self, CommonError> {
+        let mut keys = JwtKeys::new();
+        for entry in &config.jwt.keys {
+            let material = base64_decode(entry.key.expose_secret().trim()).map_err(|e| {
+                CommonError::config(format!(
+                    "JWT key '{}' is not valid base64: {}",
+                    entry.kid, e
+                ))
+            })?;
+            let key = match entry.algorithm.parse()? {
+                JwtAlgorithm::HS256 if entry.public => {
+                    return Err(CommonError::config(format!(
+                        "JWT key '{}': HS256 has no public key form",
+                        entry.kid
+                    )));
+                }
+                JwtAlgorithm::HS256 => JwtKey::Hs256(Secret::new(material)),
+                JwtAlgorithm::EdDSA if entry.public => {
+                    let public = PublicKey::from_bytes(&material).map_err(|_| {
+                        CommonError::config(format!(
+                            "JWT key '{}' must be a 32-byte Ed25519 public key",
+                            entry.kid
+                        ))
+                    })?;
+                    JwtKey::Ed25519Verifying(public)
+                }
+                JwtAlgorithm::EdDSA => {
+                    let keypair = KeyPair::from_bytes(&material).map_err(|_| {
+                        CommonError::config(format!(
+                            "JWT key '{}' must be a 32-byte Ed25519 seed",
+                            entry.kid
+                        ))
+                    })?;
+                    JwtKey::Ed25519Signing(keypair)
+                }
+            };
+            keys = keys.with_key(entry.kid.clone(), key)?;
+        }
+        if let Some(kid) = &config.jwt.signing_kid {
+            keys = keys.with_signing_kid(kid.clone())?;
+        }
+        Ok(keys)
+    }
+
+    /// Add a key; the first key added becomes the signing key unless overridden
+    pub fn with_key(mut self, kid: impl Into<String>, key: JwtKey) -> Result<Self, CommonError> {
+        if let JwtKey::Hs256(secret) = &key
+            && secret.expose_secret().len() < MIN_HS256_SECRET_LEN
+        {
+            return Err(CommonError::config(format!(
+                "HS256 secret must be at least {} bytes",
+                MIN_HS256_SECRET_LEN
+            )));
+        }
+        let kid = kid.into();
+        if self.signing_kid.is_none() && !matches!(key, JwtKey::Ed25519Verifying(_)) {
+            self.signing_kid = Some(kid.clone());
+        }
+        self.keys.insert(kid, key);
+        Ok(self)
+    }
+
+    /// Add an HS256 secret
+    ///
+    /// # Panics
+    ///
+    /// If the secret is shorter than [`MIN_HS256_SECRET_LEN`]; use
+    /// [`with_key`](Self::with_key) to handle that as an error.
+    pub fn with_hs256(self, kid: impl Into<String>, secret: &[u8]) -> Self {
+        self.with_key(kid, JwtKey::Hs256(Secret::new(secret.to_vec())))
+            .expect("HS256 secret too short")
+    }
+
+    /// Add an Ed25519 private key
+    pub fn with_ed25519(self, kid: impl Into<String>, key: KeyPair) -> Self {
+        self.with_key(kid, JwtKey::Ed25519Signing(key))
+            .expect("Ed25519 keys are always accepted")
+    }
+
+    /// Add an Ed25519 public key for verification only
+    pub fn with_ed25519_public(self, kid: impl Into<String>, key: PublicKey) -> Self {
+        self.with_key(kid, JwtKey::Ed25519Verifying(key))
+            .expect("Ed25519 keys are always accepted")
+    }
+
+    /// Choose which key issues tokens
+    ///
+    /// Fails if `kid` has not been added or is a verify-only public key.
+    pub fn with_signing_kid(mut self, kid: impl Into<String>) -> Result<Self, CommonError> {
+        let kid = kid.into();
+        match self.keys.get(&kid) {
+            None => {
+                return Err(CommonError::config(format!(
+                    "JWT signing key '{}' not found",
+                    kid
+                )));
+            }
+            Some(JwtKey::Ed25519Verifying(_)) => {
+                return Err(CommonError::config(format!(
+                    "JWT key '{}' is a public key and cannot sign",
+                    kid
+                )));
+            }
+            Some(_) => {}
+        }
+        self.signing_kid = Some(kid);
+        Ok(self)
+    }
+
+    /// Look up a key by id
+    pub fn get(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.get(kid)
+    }
+}
+
+/// Registered claims plus typed custom claims
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims<T> {
+    /// Issuer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// Subject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    /// Audience; a single string or an array in the token
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_audience",
+        deserialize_with = "deserialize_audience"
+    )]
+    pub aud: Vec<String>,
+    /// Expiration time (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<u64>,
+    /// Not before (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<u64>,
+    /// Issued at (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+    /// Custom claims, flattened into the payload
+    #[serde(flatten)]
+    pub custom: T,
+}
+
+impl<T> Claims<T> {
+    /// Claims issued now with the given custom claims
+    pub fn new(custom: T) -> Self {
+        Claims {
+            iss: None,
+            sub: None,
+            aud: Vec::new(),
+            exp: None,
+            nbf: None,
+            iat: Some(current_timestamp()),
+            custom,
+        }
+    }
+
+    /// Set the issuer
+    pub fn with_issuer(mut self, iss: impl Into<String>) -> Self {
+        self.iss = Some(iss.into());
+        self
+    }
+
+    /// Set the subject
+    pub fn with_subject(mut self, sub: impl Into<String>) -> Self {
+        self.sub = Some(sub.into());
+        self
+    }
+
+    /// Add an audience
+    pub fn with_audience(mut self, aud: impl Into<String>) -> Self {
+        self.aud.push(aud.into());
+        self
+    }
+
+    /// Expire `seconds` after `iat` (or now)
+    pub fn expires_in(mut self, seconds: u64) -> Self {
+        self.exp = Some(self.iat.unwrap_or_else(current_timestamp) + seconds);
+        self
+    }
+
+    /// Set the not-before time
+    pub fn not_before(mut self, nbf: u64) -> Self {
+        self.nbf = Some(nbf);
+        self
+    }
+}
+
+/// Rules applied by [`verify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validation {
+    /// Allowed clock skew in seconds for `exp` and `nbf`
+    pub leeway: u64,
+    /// Reject tokens without `exp`
+    pub require_exp: bool,
+    /// Required issuer, if any
+    pub issuer: Option<String>,
+    /// Required audience, if any; must be one of the token's audiences
+    pub audience: Option<String>,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Validation {
+            leeway: 60,
+            require_exp: true,
+            issuer: None,
+            audience: None,
+        }
+    }
+}
+
+impl Validation {
+    /// Validation rules from `Config::jwt`
+    pub fn from_config(config: &Config) -> Self {
+        Validation {
+            leeway: config.jwt.leeway_seconds,
+            issuer: config.jwt.issuer.clone(),
+            audience: config.jwt.audience.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    alg: JwtAlgorithm,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+/// Sign `claims` with the signing key
+pub fn issue<T: Serialize>(keys: &JwtKeys, claims: &Claims<T>) -> Result<String, CommonError> {
+    let kid = keys
+        .signing_kid
+        .as_deref()
+        .ok_or_else(|| CommonError::config("no JWT signing key configured"))?;
+    let key = keys
+        .get(kid)
+        .ok_or_else(|| CommonError::config(format!("JWT signing key '{}' not found", kid)))?;
+
+    let header = Header {
+        alg: key.algorithm(),
+        typ: Some("JWT".to_string()),
+        kid: Some(kid.to_string()),
+    };
+    let mut token = format!("{}.{}", encode_json(&header)?, encode_json(claims)?);
+    let signature = key.sign(token.as_bytes())?;
+    token.push('.');
+    token.push_str(&base64url_encode(signature));
+    Ok(token)
+}
+
+/// Verify the signature and claims of `token` against the current time
+pub fn verify<T: DeserializeOwned>(
+    keys: &JwtKeys,
+    token: &str,
+    validation: &Validation,
+) -> Result<Claims<T>, CommonError> {
+    verify_at(keys, token, validation, current_timestamp())
+}
+
+/// Verify the signature and claims of `token` against an explicit `now` (Unix seconds)
+pub fn verify_at<T: DeserializeOwned>(
+    keys: &JwtKeys,
+    token: &str,
+    validation: &Validation,
+    now: u64,
+) -> Result<Claims<T>, CommonError> {
+    let mut parts = token.split('.');
+    let (Some(header_b64), Some(payload_b64), Some(signature_b64), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("expected three dot-separated segments"));
+    };
+
+    let header: Header = decode_json(header_b64)?;
+    let key = match &header.kid {
+        Some(kid) => keys
+            .get(kid)
+            .ok_or_else(|| invalid(format!("unknown key id '{}'", kid)))?,
+        None if keys.keys.len() == 1 => keys.keys.values().next().expect("one key"),
+        None => return Err(invalid("missing key id")),
+    };
+    if header.alg != key.algorithm() {
+        return Err(invalid(format!(
+            "algorithm {:?} not allowed for this key",
+            header.alg
+        )));
+    }
+
+    let signature =
+        base64url_decode(signature_b64).map_err(|_| invalid("signature is not base64url"))?;
+    let signed = &token[..header_b64.len() + 1 + payload_b64.len()];
+    if !key.verify(signed.as_bytes(), &signature) {
+        return Err(invalid("signature mismatch"));
+    }
+
+    let claims: Claims<T> = decode_json(payload_b64)?;
+    validate_claims(&claims, validation, now)?;
+    Ok(claims)
+}
+
+fn validate_claims<T>(
+    claims: &Claims<T>,
+    validation: &Validation,
+    now: u64,
+) -> Result<(), CommonError> {
+    match claims.exp {
+        Some(exp) if now > exp.saturating_add(validation.leeway) => {
+            return Err(CommonError::TokenExpired);
+        }
+        None if validation.require_exp => {
+            return Err(CommonError::InvalidClaim("missing exp".to_string()));
+        }
+        _ => {}
+    }
+
+    if let Some(nbf) = claims.nbf
+        && now.saturating_add(validation.leeway) < nbf
+    {
+        return Err(CommonError::TokenNotYetValid);
+    }
+
+    if let Some(iss) = &validation.issuer
+        && claims.iss.as_ref() != Some(iss)
+    {
+        return Err(CommonError::InvalidClaim("issuer mismatch".to_string()));
+    }
+
+    if let Some(aud) = &validation.audience
+        && !claims.aud.contains(aud)
+    {
+        return Err(CommonError::InvalidClaim("audience mismatch".to_string()));
+    }
+
+    Ok(())
+}
+
+fn invalid(msg: impl Into<String>) -> CommonError {
+    CommonError::InvalidToken(msg.into())
+}
+
+fn encode_json<T: Serialize>(value: &T) -> Result<String, CommonError> {
+    let json = serde_json::to_vec(value)
+        .map_err(|e| CommonError::Other(format!("JWT serialization failed: {}", e)))?;
+    Ok(base64url_encode(json))
+}
+
+fn decode_json<T: DeserializeOwned>(segment: &str) -> Result<T, CommonError> {
+    let json = base64url_decode(segment).map_err(|_| invalid("segment is not base64url"))?;
+    serde_json::from_slice(&json).map_err(|e| invalid(format!("malformed JSON: {}", e)))
+}
+
+fn serialize_audience<S: Serializer>(aud: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    match aud {
+        [single] => serializer.serialize_str(single),
+        many => many.serialize(serializer),
+    }
+}
+
+fn deserialize_audience<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(aud) => vec![aud],
+        OneOrMany::Many(aud) => aud,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn rfc7515_style_structure() {
+        let keys = JwtKeys::new().with_hs256("k1", SECRET);
+        let token = issue(&keys, &Claims::new(()).expires_in(60)).unwrap();
+        assert_eq!(token.split('.').count(), 3);
+        let header: Header = decode_json(token.split('.').next().unwrap()).unwrap();
+        assert_eq!(header.alg, JwtAlgorithm::HS256);
+        assert_eq!(header.kid.as_deref(), Some("k1"));
+    }
+
+    #[test]
+    fn alg_confusion_is_rejected() {
+        let ed = KeyPair::from_bytes(&[7u8; 32]).unwrap();
+        let keys = JwtKeys::new().with_ed25519("ed", ed.clone());
+        let token = issue(&keys, &Claims::new(()).expires_in(60)).unwrap();
+
+        // Re-sign the same header/payload as HS256 using the public key bytes as secret
+        let forged_header = encode_json(&Header {
+            alg: JwtAlgorithm::HS256,
+            typ: None,
+            kid: Some("ed".to_string()),
+        })
+        .unwrap();
+        let payload = token.split('.').nth(1).unwrap();
+        let signed = format!("{}.{}", forged_header, payload);
+        let sig = hmac_sha256(&ed.public_key().to_bytes(), signed.as_bytes());
+        let forged = format!("{}.{}", signed, base64url_encode(sig));
+
+        let err = verify::<()>(&keys, &forged, &Validation::default()).unwrap_err();
+        assert!(matches!(err, CommonError::InvalidToken(_)));
+    }
+
+    #[test]
+    fn signing_kid_must_be_able_to_sign() {
+        let ed = KeyPair::from_bytes(&[7u8; 32]).unwrap();
+        let keys = JwtKeys::new()
+            .with_hs256("k1", SECRET)
+            .with_ed25519_public("ed-pub", ed.public_key());
+        assert!(keys.clone().with_signing_kid("missing").is_err());
+        assert!(keys.clone().with_signing_kid("ed-pub").is_err());
+        assert!(keys.with_signing_kid("k1").is_ok());
+    }
+
+    #[test]
+    fn audience_accepts_string_or_array() {
+        let one: Claims<()> = serde_json::from_str(r#"{"aud":"api"}"#).unwrap();
+        assert_eq!(one.aud, vec!["api"]);
+        let many: Claims<()> = serde_json::from_str(r#"{"aud":["api","web"]}"#).unwrap();
+        assert_eq!(many.aud, vec!["api", "web"]);
+        assert_eq!(serde_json::to_string(&one).unwrap(), r#"{"aud":"api"}"#);
+    }
+}
@@ -0,0 +1,157 @@
+//! Key derivation: HKDF-SHA256 for subkeys, PBKDF2-SHA256 for passwords.
+//!
+//! Derive one key per purpose from a master key instead of sharing the master
+//! key between features; see [`Keyring::derive`](super::Keyring::derive).
+//! For storing password hashes use [`password`](super::password) instead.
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use super::{CryptoError, OsRandom, RandomSource, Secret};
+
+/// Largest HKDF-SHA256 output (255 blocks of 32 bytes)
+pub const MAX_HKDF_OUTPUT_LEN: usize = 255 * 32;
+
+/// Minimum salt length accepted by [`pbkdf2_sha256`]
+pub const MIN_PBKDF2_SALT_LEN: usize = 16;
+
+/// Length of salts created by [`Salt::random`]
+pub const RANDOM_SALT_LEN: usize = 32;
+
+/// Salt for a key derivation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Salt(Vec<u8>);
+
+impl Salt {
+    /// Salt with the given bytes
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        Salt(bytes.into())
+    }
+
+    /// No salt; allowed for HKDF when the input key is already uniformly random
+    pub fn none() -> Self {
+        Salt(Vec::new())
+    }
+
+    /// Fresh random salt of [`RANDOM_SALT_LEN`] bytes
+    pub fn random() -> Result<Self, CryptoError> {
+        Self::random_with_rng(&mut OsRandom)
+    }
+
+    /// Random salt drawn from `rng`
+    pub fn random_with_rng(rng: &mut impl RandomSource) -> Result<Self, CryptoError> {
+        super::generate_random_bytes_with_rng(RANDOM_SALT_LEN, rng).map(Salt)
+    }
+
+    /// Raw salt bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Context label binding a derived key to one purpose, e.g. `"cookie-signing"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Info(Vec<u8>);
+
+impl Info {
+    /// Label with the given bytes
+    pub fn new(label: impl Into<Vec<u8>>) -> Self {
+        Info(label.into())
+    }
+
+    /// Raw label bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<&str> for Info {
+    fn from(label: &str) -> Self {
+        Info::new(label)
+    }
+}
+
+/// Derive `len` bytes from input key material with HKDF-SHA256 (RFC 5869)
+pub fn hkdf_sha256(
+    ikm: &[u8],
+    salt: &Salt,
+    info: &Info,
+    len: usize,
+) -> Result<Secret<Vec<u8>>, CryptoError> {
+    if len == 0 || len > MAX_HKDF_OUTPUT_LEN {
+        return Err(CryptoError::InvalidInput(format!(
+            "HKDF output length must be 1..={}, got {}",
+            MAX_HKDF_OUTPUT_LEN, len
+        )));
+    }
+    let salt = (!salt.as_bytes().is_empty()).then_some(salt.as_bytes());
+    let mut okm = vec![0u8; len];
+    Hkdf::<Sha256>::new(salt, ikm)
+        .expand(info.as_bytes(), &mut okm)
+        .map_err(|e| CryptoError::OperationFailed(format!("HKDF expand failed: {}", e)))?;
+    Ok(Secret::new(okm))
+}
+
+/// Derive `len` bytes from a password with PBKDF2-HMAC-SHA256
+pub fn pbkdf2_sha256(
+    password: &[u8],
+    salt: &Salt,
+    rounds: u32,
+    len: usize,
+) -> Result<Secret<Vec<u8>>, CryptoError> {
+    if salt.as_bytes().len() < MIN_PBKDF2_SALT_LEN {
+        return Err(CryptoError::InvalidInput(format!(
+            "PBKDF2 salt must be at least {} bytes, got {}",
+            MIN_PBKDF2_SALT_LEN,
+            salt.as_bytes().len()
+        )));
+    }
+    if rounds == 0 || len == 0 {
+        return Err(CryptoError::InvalidInput(
+            "PBKDF2 rounds and output length must be non-zero".to_string(),
+        ));
+    }
+    let mut out = vec![0u8; len];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, salt.as_bytes(), rounds, &mut out);
+    Ok(Secret::new(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::encoding::hex_encode as hex;
+
+    #[test]
+    fn rfc5869_case_1() {
+        let okm = hkdf_sha256(
+            &[0x0b; 22],
+            &Salt::new((0x00..=0x0c).collect::<Vec<u8>>()),
+            &Info::new((0xf0..=0xf9).collect::<Vec<u8>>()),
+            42,
+        )
+        .unwrap();
+        assert_eq!(
+            hex(okm.expose_secret()),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+             34007208d5b887185865"
+        );
+    }
+
+    #[test]
+    fn pbkdf2_known_answer() {
+        let out = pbkdf2_sha256(b"password", &Salt::new(*b"NaClNaClNaClNaCl"), 1000, 32).unwrap();
+        assert_eq!(
+            hex(out.expose_secret()),
+            "ffc75fe2d115d7c2a518b4224042f3b8155aa82d510295248e62bbd08a7b60f4"
+        );
+        assert!(pbkdf2_sha256(b"pw", &Salt::new(*b"short"), 1, 32).is_err());
+    }
+
+    #[test]
+    fn labels_separate_keys() {
+        let a = hkdf_sha256(b"master", &Salt::none(), &"cookie".into(), 32).unwrap();
+        let b = hkdf_sha256(b"master", &Salt::none(), &"tokens".into(), 32).unwrap();
+        assert_ne!(a.expose_secret(), b.expose_secret());
+        assert!(hkdf_sha256(b"master", &Salt::none(), &"x".into(), 0).is_err());
+    }
+}
@@ -0,0 +1,374 @@
+//! Named, versioned encryption keys used by [`seal`](super::seal) and [`open`](super::open).
+//!
+//! A keyring holds one primary key, used for all new envelopes, and any number
+//! of retired keys that are kept only to decrypt data sealed before a rotation.
+//! [`Keyring::reencrypt`] upgrades such envelopes to the current primary key.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::encoding::base64_decode;
+use super::kdf::{Info, Salt, hkdf_sha256};
+use super::{Algorithm, CryptoError, Envelope, Secret, aes::KEY_LEN};
+use crate::config::Config;
+
+/// Maximum length of a key id, so it fits the one-byte length in the envelope header
+pub const MAX_KID_LEN: usize = 255;
+
+/// Key id used for the legacy single `EncryptionConfig::key`
+pub const LEGACY_KID: &str = "default";
+
+/// Prefix of the HKDF info label used by [`Keyring::derive`]
+pub const DERIVE_INFO_PREFIX: &str = "cland-rust-share/keyring/";
+
+/// Lifecycle state of a key in the keyring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// Usable for encryption (when primary) and decryption
+    Active,
+    /// Usable for decryption only
+    Retired,
+}
+
+/// Public metadata of a key; never includes key material
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyInfo {
+    /// Key id recorded in envelopes
+    pub kid: String,
+    /// Monotonic version, in the order keys were added to the keyring
+    pub version: u32,
+    /// Lifecycle state
+    pub status: KeyStatus,
+    /// Whether this is the key used for encryption
+    pub primary: bool,
+}
+
+#[derive(Clone)]
+struct KeyEntry {
+    version: u32,
+    status: KeyStatus,
+    material: Secret<[u8; KEY_LEN]>,
+}
+
+/// A set of named 256-bit keys with one primary key used for encryption
+#[derive(Clone)]
+pub struct Keyring {
+    keys: BTreeMap<String, KeyEntry>,
+    primary: String,
+    algorithm: Algorithm,
+    next_version: u32,
+}
+
+impl Keyring {
+    /// Create a keyring whose primary key is `key`, identified by `kid`
+    pub fn new(kid: impl Into<String>, key: impl AsRef<[u8]>) -> Result<Self, CryptoError> {
+        let kid = kid.into();
+        let mut keyring = Keyring {
+            keys: BTreeMap::new(),
+            primary: kid.clone(),
+            algorithm: Algorithm::default(),
+            next_version: 1,
+        };
+        keyring.insert(kid, key.as_ref(), KeyStatus::Active)?;
+        Ok(keyring)
+    }
+
+    /// Build a keyring from `EncryptionConfig`
+    ///
+    /// Keys listed in `encryption.keys` are base64-encoded and versioned in the
+    /// order they are listed. The primary key is `encryption.primary_kid`, or the
+    /// last listed key when unset; every other key is retired. A non-empty legacy
+    /// `encryption.key` is added as a raw 32-byte key with id [`LEGACY_KID`].
+    pub fn from_config(config: &Config) -> Result<Self, CryptoError> {
+        let enc = &config.encryption;
+        let algorithm: Algorithm = enc.algorithm.parse()?;
+
+        let mut entries: Vec<(String, Secret<Vec<u8>>)> = Vec::new();
+        let legacy = enc.key.expose_secret();
+        if !legacy.is_empty() {
+            entries.push((
+                LEGACY_KID.to_string(),
+                Secret::new(legacy.as_bytes().to_vec()),
+            ));
+        }
+        for entry in &enc.keys {
+            let material = base64_decode(entry.key.expose_secret().trim()).map_err(|e| {
+                CryptoError::InvalidInput(format!("key '{}' is not valid base64: {}", entry.kid, e))
+            })?;
+            entries.push((entry.kid.clone(), Secret::new(material)));
+        }
+
+        let primary = match &enc.primary_kid {
+            Some(kid) => kid.clone(),
+            None => entries.last().map(|(kid, _)| kid.clone()).ok_or_else(|| {
+                CryptoError::InvalidInput("no encryption keys configured".to_string())
+            })?,
+        };
+        let mut keyring = Keyring {
+            keys: BTreeMap::new(),
+            primary: primary.clone(),
+            algorithm,
+            next_version: 1,
+        };
+        for (kid, material) in entries {
+            let status = if kid == primary {
+                KeyStatus::Active
+            } else {
+                KeyStatus::Retired
+            };
+            keyring.insert(kid, material.expose_secret(), status)?;
+        }
+        if !keyring.keys.contains_key(&primary) {
+            return Err(CryptoError::UnknownKeyId(primary));
+        }
+        Ok(keyring)
+    }
+
+    /// Set the algorithm used for new envelopes
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Add a retired key that can only be used for decryption
+    pub fn add_key(
+        &mut self,
+        kid: impl Into<String>,
+        key: impl AsRef<[u8]>,
+    ) -> Result<(), CryptoError> {
+        self.insert(kid.into(), key.as_ref(), KeyStatus::Retired)
+    }
+
+    /// Add `key` as the new primary key and retire the previous primary
+    pub fn rotate(
+        &mut self,
+        kid: impl Into<String>,
+        key: impl AsRef<[u8]>,
+    ) -> Result<(), CryptoError> {
+        let kid = kid.into();
+        self.insert(kid.clone(), key.as_ref(), KeyStatus::Active)?;
+        self.set_primary(&kid)
+    }
+
+    /// Make an existing key primary and retire the previous primary
+    pub fn set_primary(&mut self, kid: &str) -> Result<(), CryptoError> {
+        let entry = self
+            .keys
+            .get_mut(kid)
+            .ok_or_else(|| CryptoError::UnknownKeyId(kid.to_string()))?;
+        entry.status = KeyStatus::Active;
+
+        if self.primary != kid {
+            if let Some(old) = self.keys.get_mut(&self.primary) {
+                old.status = KeyStatus::Retired;
+            }
+            self.primary = kid.to_string();
+        }
+        Ok(())
+    }
+
+    /// Id of the key used for encryption
+    pub fn primary_kid(&self) -> &str {
+        &self.primary
+    }
+
+    /// Algorithm used for new envelopes
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Look up key material by id
+    pub fn get(&self, kid: &str) -> Option<&[u8; KEY_LEN]> {
+        self.keys.get(kid).map(|e| e.material.expose_secret())
+    }
+
+    /// Metadata of all keys, ordered by version
+    pub fn keys(&self) -> Vec<KeyInfo> {
+        let mut infos: Vec<KeyInfo> = self
+            .keys
+            .iter()
+            .map(|(kid, e)| KeyInfo {
+                kid: kid.clone(),
+                version: e.version,
+                status: e.status,
+                primary: *kid == self.primary,
+            })
+            .collect();
+        infos.sort_by_key(|i| i.version);
+        infos
+    }
+
+    /// Whether `envelope` was sealed with something other than the primary key and algorithm
+    pub fn needs_reencrypt(&self, envelope: &Envelope) -> bool {
+        envelope.key_id() != self.primary || envelope.algorithm() != self.algorithm
+    }
+
+    /// Re-seal `envelope` under the primary key if it was sealed with an older key or algorithm
+    pub fn reencrypt(&self, envelope: &Envelope) -> Result<Envelope, CryptoError> {
+        self.reencrypt_with_aad(envelope, &[])
+    }
+
+    /// Same as [`reencrypt`](Self::reencrypt) for envelopes sealed with associated data
+    pub fn reencrypt_with_aad(
+        &self,
+        envelope: &Envelope,
+        aad: &[u8],
+    ) -> Result<Envelope, CryptoError> {
+        if !self.needs_reencrypt(envelope) {
+            return Ok(envelope.clone());
+        }
+        let plaintext = super::open_with_aad(self, envelope, aad)?;
+        super::seal_with_aad(self, &plaintext, aad)
+    }
+
+    /// Keyring for one purpose, e.g. `"db-field"` or `"cookie-signing"`
+    ///
+    /// Every key is replaced by an HKDF-SHA256 subkey bound to `purpose`, keeping
+    /// its id, version and status, so rotation and re-encryption work the same
+    /// way on the derived keyring and the master keys never leave this one.
+    pub fn derive(&self, purpose: &str) -> Result<Keyring, CryptoError> {
+        let mut derived = self.clone();
+        for entry in derived.keys.values_mut() {
+            entry.material = derive_subkey(entry.material.expose_secret(), purpose)?;
+        }
+        Ok(derived)
+    }
+
+    /// Subkey of the primary key for `purpose`, e.g. an HMAC key for cookie signing
+    pub fn derive_key(&self, purpose: &str) -> Result<Secret<[u8; KEY_LEN]>, CryptoError> {
+        derive_subkey(self.primary().1, purpose)
+    }
+
+    /// Primary key id and material
+    pub(crate) fn primary(&self) -> (&str, &[u8; KEY_LEN]) {
+        (
+            &self.primary,
+            self.keys[&self.primary].material.expose_secret(),
+        )
+    }
+
+    /// Key material for `kid`, or [`CryptoError::UnknownKeyId`]
+    pub(crate) fn require(&self, kid: &str) -> Result<&[u8; KEY_LEN], CryptoError> {
+        self.get(kid)
+            .ok_or_else(|| CryptoError::UnknownKeyId(kid.to_string()))
+    }
+
+    fn insert(&mut self, kid: String, key: &[u8], status: KeyStatus) -> Result<(), CryptoError> {
+        validate_kid(&kid)?;
+        if self.keys.contains_key(&kid) {
+            return Err(CryptoError::InvalidInput(format!(
+                "duplicate key id '{}'",
+                kid
+            )));
+        }
+
+        let material: [u8; KEY_LEN] =
+            key.try_into().map_err(|_| CryptoError::InvalidKeyLength {
+                expected: KEY_LEN,
+                actual: key.len(),
+            })?;
+        let version = self.bump_version();
+        self.keys.insert(
+            kid,
+            KeyEntry {
+                version,
+                status,
+                material: Secret::new(material),
+            },
+        );
+        Ok(())
+    }
+
+    fn bump_version(&mut self) -> u32 {
+        let version = self.next_version;
+        self.next_version += 1;
+        version
+    }
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print key material
+        f.debug_struct("Keyring")
+            .field("keys", &self.keys())
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+fn derive_subkey(
+    master: &[u8; KEY_LEN],
+    purpose: &str,
+) -> Result<Secret<[u8; KEY_LEN]>, CryptoError> {
+    if purpose.is_empty() {
+        return Err(CryptoError::InvalidInput(
+            "key derivation purpose must not be empty".to_string(),
+        ));
+    }
+    let info = Info::new(format!("{}{}", DERIVE_INFO_PREFIX, purpose));
+    let okm = hkdf_sha256(master, &Salt::none(), &info, KEY_LEN)?;
+    let mut key = [0u8; KEY_LEN];
+    key.copy_from_slice(okm.expose_secret());
+    Ok(Secret::new(key))
+}
+
+fn validate_kid(kid: &str) -> Result<(), CryptoError> {
+    if kid.is_empty() || kid.len() > MAX_KID_LEN {
+        return Err(CryptoError::InvalidInput(format!(
+            "key id must be 1..={} bytes, got {}",
+            MAX_KID_LEN,
+            kid.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_retires_previous_primary() {
+        let mut ring = Keyring::new("k1", [1u8; 32]).unwrap();
+        ring.rotate("k2", [2u8; 32]).unwrap();
+
+        let keys = ring.keys();
+        assert_eq!(ring.primary_kid(), "k2");
+        assert_eq!(keys[0].kid, "k1");
+        assert_eq!(keys[0].status, KeyStatus::Retired);
+        assert_eq!(keys[1].version, 2);
+        assert!(keys[1].primary);
+    }
+
+    #[test]
+    fn duplicate_kid_is_rejected() {
+        let mut ring = Keyring::new("k1", [1u8; 32]).unwrap();
+        assert!(ring.add_key("k1", [2u8; 32]).is_err());
+        assert_eq!(ring.get("k1"), Some(&[1u8; 32]));
+    }
+
+    #[test]
+    fn derived_keyring_keeps_ids_and_separates_purposes() {
+        let mut ring = Keyring::new("k1", [1u8; 32]).unwrap();
+        ring.rotate("k2", [2u8; 32]).unwrap();
+
+        let cookies = ring.derive("cookie-signing").unwrap();
+        let fields = ring.derive("db-field").unwrap();
+        assert_eq!(cookies.keys(), ring.keys());
+        assert_ne!(cookies.get("k1"), ring.get("k1"));
+        assert_ne!(cookies.get("k2"), fields.get("k2"));
+        assert_eq!(
+            cookies.get("k2"),
+            Some(ring.derive_key("cookie-signing").unwrap().expose_secret())
+        );
+        assert!(ring.derive("").is_err());
+    }
+
+    #[test]
+    fn debug_hides_material() {
+        let ring = Keyring::new("k1", [0xAB; 32]).unwrap();
+        let s = format!("{:?}", ring);
+        assert!(s.contains("k1"));
+        assert!(!s.contains("171"));
+    }
+}
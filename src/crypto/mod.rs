@@ -17,48 +17,55 @@ pub enum CryptoError {
     /// Encryption/decryption failed
     #[error("Crypto operation failed: {0}")]
     OperationFailed(String),
-}
-
-/// AES-256-GCM encryption
-pub fn aes_encrypt(plaintext: &str, key: &str) -> Result<Vec<u8>, CryptoError> {
-    if key.len() != 32 {
-        return Err(CryptoError::InvalidKeyLength {
-            expected: 32,
-            actual: key.len(),
-        });
-    }
-
-    // Placeholder implementation - in real scenario, use proper crypto library
-    // like ring or openssl
-    let mut result = Vec::from(plaintext.as_bytes());
-    result.extend_from_slice(key.as_bytes());
-    Ok(result)
-}
 
-/// AES-256-GCM decryption
-pub fn aes_decrypt(ciphertext: &[u8], key: &str) -> Result<String, CryptoError> {
-    if key.len() != 32 {
-        return Err(CryptoError::InvalidKeyLength {
-            expected: 32,
-            actual: key.len(),
-        });
-    }
+    /// Envelope format version not understood by this build
+    #[error("Unsupported envelope version: {0}")]
+    UnsupportedVersion(u8),
 
-    if ciphertext.len() <= key.len() {
-        return Err(CryptoError::InvalidInput(
-            "Ciphertext too short".to_string(),
-        ));
-    }
+    /// Algorithm not supported by this build
+    #[error("Unsupported algorithm: {0}")]
+    UnsupportedAlgorithm(String),
 
-    // Placeholder implementation
-    let plaintext_len = ciphertext.len() - key.len();
-    let plaintext_bytes = &ciphertext[..plaintext_len];
+    /// Key id not present in the keyring
+    #[error("Unknown key id: {0}")]
+    UnknownKeyId(String),
 
-    String::from_utf8(plaintext_bytes.to_vec())
-        .map_err(|e| CryptoError::InvalidInput(format!("Invalid UTF-8: {}", e)))
+    /// Failure described only by its message, as built by [`CommonError::crypto`](crate::CommonError::crypto)
+    #[error("{0}")]
+    Other(String),
 }
 
+pub mod aes;
+pub mod encoding;
+pub mod envelope;
+pub mod field;
+pub mod hmac;
+pub mod jwt;
+pub mod kdf;
+pub mod keyring;
+pub mod obfuscate;
+pub mod otp;
+pub mod password;
+pub mod random;
+pub mod secret;
+pub mod sign;
+pub mod stream;
+
+pub use aes::{
+    aes_decrypt, aes_decrypt_with_aad, aes_encrypt, aes_encrypt_with_aad, aes_encrypt_with_rng,
+};
+pub use encoding::{EncodeExt, EncodingError};
+pub use envelope::{Algorithm, Envelope, open, open_with_aad, seal, seal_with_aad, seal_with_rng};
+pub use field::{EncryptedField, Searchable};
+pub use hmac::{hmac_sha256, hmac_sha512, verify_hmac_sha256, verify_hmac_sha512};
+pub use keyring::{KeyInfo, KeyStatus, Keyring};
+pub use random::{Alphabet, OsRandom, RandomSource, SeededRandom};
+pub use secret::Secret;
+pub use stream::{StreamDecryptor, StreamEncryptor, decrypt_file, encrypt_file};
+
 /// Generate SHA-256 hash
+///
+/// Unsalted and fast, so not suitable for passwords; use [`password::hash`] instead.
 pub fn sha256_hash(data: &[u8]) -> Vec<u8> {
     use sha2::{Digest, Sha256};
 
@@ -67,51 +74,55 @@ pub fn sha256_hash(data: &[u8]) -> Vec<u8> {
     hasher.finalize().to_vec()
 }
 
+/// Compare two byte slices in constant time with respect to their contents
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// Generate secure random bytes
 pub fn generate_random_bytes(length: usize) -> Result<Vec<u8>, CryptoError> {
-    use rand::RngCore;
+    generate_random_bytes_with_rng(length, &mut OsRandom)
+}
 
-    let mut rng = rand::thread_rng();
+/// Generate random bytes from `rng`
+pub fn generate_random_bytes_with_rng(
+    length: usize,
+    rng: &mut impl RandomSource,
+) -> Result<Vec<u8>, CryptoError> {
     let mut bytes = vec![0u8; length];
-    rng.fill_bytes(&mut bytes);
+    rng.fill_random(&mut bytes)?;
     Ok(bytes)
 }
 
-/// Generate secure random string
+/// Generate secure random alphanumeric string
 pub fn generate_random_string(length: usize) -> Result<String, CryptoError> {
-    use rand::Rng;
+    generate_random_string_from(length, &Alphabet::alphanumeric())
+}
 
-    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
-                            abcdefghijklmnopqrstuvwxyz\
-                            0123456789";
+/// Generate secure random string from `alphabet`, e.g. [`Alphabet::digits`] for SMS codes
+pub fn generate_random_string_from(
+    length: usize,
+    alphabet: &Alphabet,
+) -> Result<String, CryptoError> {
+    generate_random_string_with_rng(length, alphabet, &mut OsRandom)
+}
 
-    let mut rng = rand::thread_rng();
+/// Generate random string from `alphabet` using `rng`
+pub fn generate_random_string_with_rng(
+    length: usize,
+    alphabet: &Alphabet,
+    rng: &mut impl RandomSource,
+) -> Result<String, CryptoError> {
+    let chars = alphabet.chars();
     let mut result = String::with_capacity(length);
-
     for _ in 0..length {
-        let idx = rng.gen_range(0..CHARSET.len());
-        result.push(CHARSET[idx] as char);
+        result.push(chars[rng.index(chars.len())?]);
     }
-
     Ok(result)
 }
 
-/// Simple XOR encryption (for demonstration purposes)
-pub fn xor_encrypt(data: &[u8], key: &[u8]) -> Vec<u8> {
-    let mut result = Vec::with_capacity(data.len());
-
-    for (i, &byte) in data.iter().enumerate() {
-        let key_byte = key[i % key.len()];
-        result.push(byte ^ key_byte);
-    }
-
-    result
-}
-
-/// Simple XOR decryption (for demonstration purposes)
-pub fn xor_decrypt(data: &[u8], key: &[u8]) -> Vec<u8> {
-    xor_encrypt(data, key) // XOR is symmetric
-}
-
 // Re-export rand for convenience
 pub use rand;
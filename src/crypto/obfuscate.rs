@@ -0,0 +1,121 @@
+//! Reversible obfuscation of ids. Not encryption.
+//!
+//! [`IdObfuscator`] maps `u64` to `u64` with a keyed Feistel network, so
+//! sequential database ids can be shown as opaque numbers and mapped back
+//! without a lookup table. It hides ordering and counts from casual
+//! observers; it does not protect secrets. Use [`seal`](super::seal) for data
+//! that must stay confidential.
+//!
+//! The XOR helpers that used to live in `crypto` are only compiled with the
+//! `insecure-demo` feature.
+
+use super::{CryptoError, Keyring, Secret, hmac_sha256};
+
+/// Feistel rounds; four already give a pseudorandom permutation with a PRF round function
+pub const FEISTEL_ROUNDS: u32 = 8;
+
+/// Purpose label used by [`IdObfuscator::from_keyring`]
+pub const ID_PURPOSE: &str = "obfuscate/ids";
+
+/// Keyed bijection on `u64`
+#[derive(Clone)]
+pub struct IdObfuscator {
+    key: Secret<Vec<u8>>,
+}
+
+impl IdObfuscator {
+    /// Obfuscator keyed with `key`, at least 16 bytes
+    pub fn new(key: impl AsRef<[u8]>) -> Result<Self, CryptoError> {
+        let key = key.as_ref();
+        if key.len() < 16 {
+            return Err(CryptoError::InvalidKeyLength {
+                expected: 16,
+                actual: key.len(),
+            });
+        }
+        Ok(IdObfuscator {
+            key: Secret::new(key.to_vec()),
+        })
+    }
+
+    /// Obfuscator keyed with a subkey of the keyring's primary key
+    pub fn from_keyring(keyring: &Keyring) -> Result<Self, CryptoError> {
+        Self::new(keyring.derive_key(ID_PURPOSE)?.expose_secret())
+    }
+
+    /// Map a real id to its public form
+    pub fn encode(&self, id: u64) -> u64 {
+        let (mut left, mut right) = ((id >> 32) as u32, id as u32);
+        for round in 0..FEISTEL_ROUNDS {
+            (left, right) = (right, left ^ self.round(round, right));
+        }
+        ((left as u64) << 32) | right as u64
+    }
+
+    /// Map a public id back to the real id
+    pub fn decode(&self, public_id: u64) -> u64 {
+        let (mut left, mut right) = ((public_id >> 32) as u32, public_id as u32);
+        for round in (0..FEISTEL_ROUNDS).rev() {
+            (left, right) = (right ^ self.round(round, left), left);
+        }
+        ((left as u64) << 32) | right as u64
+    }
+
+    fn round(&self, round: u32, half: u32) -> u32 {
+        let mut input = [0u8; 8];
+        input[..4].copy_from_slice(&round.to_be_bytes());
+        input[4..].copy_from_slice(&half.to_be_bytes());
+        let mac = hmac_sha256(self.key.expose_secret(), &input);
+        u32::from_be_bytes([mac[0], mac[1], mac[2], mac[3]])
+    }
+}
+
+impl std::fmt::Debug for IdObfuscator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("IdObfuscator { key: *** }")
+    }
+}
+
+/// XOR `data` with a repeating `key`; trivially reversible, for demonstrations only
+#[cfg(feature = "insecure-demo")]
+pub fn xor_encrypt(data: &[u8], key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if key.is_empty() {
+        return Err(CryptoError::InvalidInput("XOR key is empty".to_string()));
+    }
+    Ok(data
+        .iter()
+        .zip(key.iter().cycle())
+        .map(|(byte, key_byte)| byte ^ key_byte)
+        .collect())
+}
+
+/// Inverse of [`xor_encrypt`] (XOR is symmetric)
+#[cfg(feature = "insecure-demo")]
+pub fn xor_decrypt(data: &[u8], key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    xor_encrypt(data, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_scrambles() {
+        let obfuscator = IdObfuscator::new([5u8; 32]).unwrap();
+        for id in [0, 1, 2, 42, u32::MAX as u64, u64::MAX - 1, u64::MAX] {
+            assert_eq!(obfuscator.decode(obfuscator.encode(id)), id);
+        }
+        assert_ne!(obfuscator.encode(1), 1);
+        assert_ne!(obfuscator.encode(1) + 1, obfuscator.encode(2));
+
+        let other = IdObfuscator::new([6u8; 32]).unwrap();
+        assert_ne!(obfuscator.encode(1), other.encode(1));
+        assert!(IdObfuscator::new(b"short").is_err());
+    }
+
+    #[cfg(feature = "insecure-demo")]
+    #[test]
+    fn xor_rejects_empty_key() {
+        assert!(xor_encrypt(b"data", b"").is_err());
+    }
+}
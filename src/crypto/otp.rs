@@ -0,0 +1,353 @@
+//! One-time passwords: HOTP (RFC 4226) and TOTP (RFC 6238).
+//!
+//! Secrets are at least 16 bytes; [`Totp::generate_secret`] draws 20 bytes
+//! from [`generate_random_bytes`](super::generate_random_bytes), the size most
+//! authenticator apps expect. Verification accepts codes within a small window
+//! around the current step and rejects any counter at or below the last one
+//! accepted, so a code cannot be replayed. Store the returned counter with the
+//! user and pass it back on the next verification.
+//!
+//! ```
+//! use cland_rust_share::crypto::otp::Totp;
+//!
+//! let totp = Totp::new(Totp::generate_secret().unwrap()).unwrap();
+//! let code = totp.generate_at(1_700_000_000);
+//! let counter = totp.verify_at(&code, 1_700_000_000, None).unwrap();
+//! assert!(totp.verify_at(&code, 1_700_000_000, Some(counter)).is_none());
+//! ```
+
+use ::hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use super::encoding::{base32_decode, base32_encode_no_pad, percent_encode};
+use super::{CryptoError, OsRandom, RandomSource, Secret, constant_time_eq};
+use crate::utils::current_timestamp;
+
+/// Shortest secret accepted (128 bits, RFC 4226 section 4)
+pub const MIN_SECRET_LEN: usize = 16;
+
+/// Length of secrets created by [`Totp::generate_secret`]
+pub const DEFAULT_SECRET_LEN: usize = 20;
+
+/// Default number of digits in a code
+pub const DEFAULT_DIGITS: u32 = 6;
+
+/// Default TOTP time step, in seconds
+pub const DEFAULT_PERIOD: u64 = 30;
+
+/// Default number of steps accepted before and after the current one
+pub const DEFAULT_WINDOW: u64 = 1;
+
+/// Largest window accepted by [`Totp::with_window`]; each step costs an HMAC
+/// and makes guessing easier
+pub const MAX_WINDOW: u64 = 10;
+
+/// HMAC hash used to compute codes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OtpAlgorithm {
+    /// HMAC-SHA1; the only algorithm every authenticator app supports
+    #[default]
+    Sha1,
+    /// HMAC-SHA256
+    Sha256,
+    /// HMAC-SHA512
+    Sha512,
+}
+
+impl OtpAlgorithm {
+    /// Name used in `otpauth://` URIs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    fn mac(self, key: &[u8], counter: u64) -> Vec<u8> {
+        fn run<M: Mac + ::hmac::digest::KeyInit>(key: &[u8], counter: u64) -> Vec<u8> {
+            let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(&counter.to_be_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+        match self {
+            OtpAlgorithm::Sha1 => run::<Hmac<Sha1>>(key, counter),
+            OtpAlgorithm::Sha256 => run::<Hmac<Sha256>>(key, counter),
+            OtpAlgorithm::Sha512 => run::<Hmac<Sha512>>(key, counter),
+        }
+    }
+}
+
+/// Counter-based one-time passwords (RFC 4226)
+#[derive(Debug, Clone)]
+pub struct Hotp {
+    secret: Secret<Vec<u8>>,
+    algorithm: OtpAlgorithm,
+    digits: u32,
+}
+
+impl Hotp {
+    /// HOTP with SHA1 and 6 digits
+    pub fn new(secret: impl Into<Vec<u8>>) -> Result<Self, CryptoError> {
+        let secret = secret.into();
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(CryptoError::InvalidKeyLength {
+                expected: MIN_SECRET_LEN,
+                actual: secret.len(),
+            });
+        }
+        Ok(Hotp {
+            secret: Secret::new(secret),
+            algorithm: OtpAlgorithm::default(),
+            digits: DEFAULT_DIGITS,
+        })
+    }
+
+    /// Use `algorithm` instead of SHA1
+    pub fn with_algorithm(mut self, algorithm: OtpAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Produce codes of 6, 7 or 8 digits
+    pub fn with_digits(mut self, digits: u32) -> Result<Self, CryptoError> {
+        if !(6..=8).contains(&digits) {
+            return Err(CryptoError::InvalidInput(format!(
+                "OTP codes must have 6 to 8 digits, got {}",
+                digits
+            )));
+        }
+        self.digits = digits;
+        Ok(self)
+    }
+
+    /// Code for `counter`, zero-padded to the configured number of digits
+    pub fn generate(&self, counter: u64) -> String {
+        let mac = self.algorithm.mac(self.secret.expose_secret(), counter);
+        // Dynamic truncation, RFC 4226 section 5.3
+        let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            mac[offset] & 0x7f,
+            mac[offset + 1],
+            mac[offset + 2],
+            mac[offset + 3],
+        ]);
+        let code = binary % 10u32.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Check `code` against counters `counter..=counter + look_ahead`
+    ///
+    /// Returns the matching counter; the next expected counter is one past it.
+    pub fn verify(&self, code: &str, counter: u64, look_ahead: u64) -> Option<u64> {
+        (counter..=counter.saturating_add(look_ahead)).find(|c| self.matches(code, *c))
+    }
+
+    fn matches(&self, code: &str, counter: u64) -> bool {
+        constant_time_eq(self.generate(counter).as_bytes(), code.trim().as_bytes())
+    }
+}
+
+/// Time-based one-time passwords (RFC 6238)
+#[derive(Debug, Clone)]
+pub struct Totp {
+    hotp: Hotp,
+    period: u64,
+    window: u64,
+}
+
+impl Totp {
+    /// TOTP with SHA1, 6 digits, a 30 second period and a window of one step
+    pub fn new(secret: impl Into<Vec<u8>>) -> Result<Self, CryptoError> {
+        Ok(Totp {
+            hotp: Hotp::new(secret)?,
+            period: DEFAULT_PERIOD,
+            window: DEFAULT_WINDOW,
+        })
+    }
+
+    /// TOTP for a base32 secret as shown by authenticator apps
+    pub fn from_base32(secret: &str) -> Result<Self, CryptoError> {
+        Self::new(base32_decode(secret)?)
+    }
+
+    /// Fresh random secret of [`DEFAULT_SECRET_LEN`] bytes
+    pub fn generate_secret() -> Result<Vec<u8>, CryptoError> {
+        Self::generate_secret_with_rng(&mut OsRandom)
+    }
+
+    /// Random secret of [`DEFAULT_SECRET_LEN`] bytes drawn from `rng`
+    pub fn generate_secret_with_rng(rng: &mut impl RandomSource) -> Result<Vec<u8>, CryptoError> {
+        super::generate_random_bytes_with_rng(DEFAULT_SECRET_LEN, rng)
+    }
+
+    /// Use `algorithm` instead of SHA1
+    pub fn with_algorithm(mut self, algorithm: OtpAlgorithm) -> Self {
+        self.hotp = self.hotp.with_algorithm(algorithm);
+        self
+    }
+
+    /// Produce codes of 6, 7 or 8 digits
+    pub fn with_digits(mut self, digits: u32) -> Result<Self, CryptoError> {
+        self.hotp = self.hotp.with_digits(digits)?;
+        Ok(self)
+    }
+
+    /// Time step in seconds
+    pub fn with_period(mut self, period: u64) -> Result<Self, CryptoError> {
+        if period == 0 {
+            return Err(CryptoError::InvalidInput(
+                "TOTP period must be non-zero".to_string(),
+            ));
+        }
+        self.period = period;
+        Ok(self)
+    }
+
+    /// Number of steps accepted before and after the current one, for clock
+    /// drift; at most [`MAX_WINDOW`]
+    pub fn with_window(mut self, window: u64) -> Result<Self, CryptoError> {
+        if window > MAX_WINDOW {
+            return Err(CryptoError::InvalidInput(format!(
+                "TOTP window must be at most {}",
+                MAX_WINDOW
+            )));
+        }
+        self.window = window;
+        Ok(self)
+    }
+
+    /// Secret as unpadded base32, for manual entry
+    pub fn secret_base32(&self) -> String {
+        base32_encode_no_pad(self.hotp.secret.expose_secret())
+    }
+
+    /// Time step containing `timestamp` (Unix seconds)
+    pub fn counter_at(&self, timestamp: u64) -> u64 {
+        timestamp / self.period
+    }
+
+    /// Code for the current time
+    pub fn generate(&self) -> String {
+        self.generate_at(current_timestamp())
+    }
+
+    /// Code for `timestamp` (Unix seconds)
+    pub fn generate_at(&self, timestamp: u64) -> String {
+        self.hotp.generate(self.counter_at(timestamp))
+    }
+
+    /// Verify `code` against the current time
+    ///
+    /// See [`verify_at`](Self::verify_at).
+    pub fn verify(&self, code: &str, last_used: Option<u64>) -> Option<u64> {
+        self.verify_at(code, current_timestamp(), last_used)
+    }
+
+    /// Verify `code` against `timestamp` (Unix seconds)
+    ///
+    /// Returns the matched time step, which the caller stores and passes back as
+    /// `last_used`. Steps at or below `last_used` are rejected even if the code
+    /// is correct.
+    pub fn verify_at(&self, code: &str, timestamp: u64, last_used: Option<u64>) -> Option<u64> {
+        let current = self.counter_at(timestamp);
+        let mut first = current.saturating_sub(self.window);
+        if let Some(last) = last_used {
+            first = first.max(last.saturating_add(1));
+        }
+        let last = current.saturating_add(self.window);
+        (first..=last).find(|c| self.hotp.matches(code, *c))
+    }
+
+    /// `otpauth://totp/...` URI for QR codes
+    ///
+    /// Issuer and account are percent-encoded; the secret is unpadded base32.
+    pub fn provisioning_uri(&self, issuer: &str, account: &str) -> String {
+        let issuer = percent_encode(issuer);
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm={}&digits={}&period={}",
+            issuer,
+            percent_encode(account),
+            self.secret_base32(),
+            issuer,
+            self.hotp.algorithm.as_str(),
+            self.hotp.digits,
+            self.period
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED_20: &[u8] = b"12345678901234567890";
+    const SEED_32: &[u8] = b"12345678901234567890123456789012";
+    const SEED_64: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    #[test]
+    fn rfc4226_appendix_d() {
+        let hotp = Hotp::new(SEED_20).unwrap();
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp.generate(counter as u64), *code);
+        }
+        assert_eq!(hotp.verify("969429", 1, 5), Some(3));
+        assert_eq!(hotp.verify("969429", 0, 2), None);
+    }
+
+    #[test]
+    fn rfc6238_appendix_b() {
+        let cases = [
+            (SEED_20, OtpAlgorithm::Sha1, ["94287082", "07081804"]),
+            (SEED_32, OtpAlgorithm::Sha256, ["46119246", "68084774"]),
+            (SEED_64, OtpAlgorithm::Sha512, ["90693936", "25091201"]),
+        ];
+        for (seed, algorithm, codes) in cases {
+            let totp = Totp::new(seed)
+                .unwrap()
+                .with_algorithm(algorithm)
+                .with_digits(8)
+                .unwrap();
+            assert_eq!(totp.generate_at(59), codes[0]);
+            assert_eq!(totp.generate_at(1111111109), codes[1]);
+        }
+    }
+
+    #[test]
+    fn window_and_replay() {
+        let totp = Totp::new(SEED_20).unwrap();
+        let now = 1_000_000;
+        let previous = totp.generate_at(now - 30);
+        let step = totp.verify_at(&previous, now, None).unwrap();
+        assert_eq!(step, totp.counter_at(now) - 1);
+        assert_eq!(totp.verify_at(&previous, now, Some(step)), None);
+        assert_eq!(totp.verify_at(&totp.generate_at(now - 60), now, None), None);
+        assert!(
+            totp.clone()
+                .with_window(2)
+                .unwrap()
+                .verify_at(&totp.generate_at(now - 60), now, None)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        assert!(Hotp::new(b"short".to_vec()).is_err());
+        assert!(Hotp::new(SEED_20).unwrap().with_digits(9).is_err());
+        assert!(Totp::new(SEED_20).unwrap().with_period(0).is_err());
+        assert!(Totp::new(SEED_20).unwrap().with_window(MAX_WINDOW).is_ok());
+        assert!(
+            Totp::new(SEED_20)
+                .unwrap()
+                .with_window(MAX_WINDOW + 1)
+                .is_err()
+        );
+        assert!(Totp::new(SEED_20).unwrap().with_window(u64::MAX).is_err());
+    }
+}
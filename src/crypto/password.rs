@@ -0,0 +1,251 @@
+//! Password hashing with Argon2id (default) or PBKDF2-SHA256.
+//!
+//! Hashes are stored as [PHC strings](https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md),
+//! e.g. `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`, so the algorithm and
+//! cost travel with the hash. Use [`needs_rehash`] after a successful
+//! [`verify`] to upgrade hashes created with older settings.
+//!
+//! Never use [`sha256_hash`](super::sha256_hash) for passwords.
+
+use argon2::password_hash::{self, PasswordHash, PasswordHasher, SaltString};
+use argon2::{Argon2, Version};
+use pbkdf2::Pbkdf2;
+
+use super::{CryptoError, OsRandom, RandomSource};
+
+/// Salt length in bytes
+const SALT_LEN: usize = 16;
+
+/// Derived hash length in bytes for PBKDF2
+const PBKDF2_OUTPUT_LEN: usize = 32;
+
+/// Password hashing algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordAlgorithm {
+    /// Argon2id (recommended)
+    Argon2id,
+    /// PBKDF2 with HMAC-SHA256, for environments that require FIPS-approved primitives
+    Pbkdf2Sha256,
+}
+
+/// Cost settings used when hashing new passwords
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordConfig {
+    /// Algorithm for new hashes
+    pub algorithm: PasswordAlgorithm,
+    /// Argon2 memory cost in KiB
+    pub argon2_memory_kib: u32,
+    /// Argon2 iterations
+    pub argon2_iterations: u32,
+    /// Argon2 parallelism
+    pub argon2_parallelism: u32,
+    /// PBKDF2 iteration count
+    pub pbkdf2_rounds: u32,
+}
+
+impl PasswordConfig {
+    /// Argon2id with the given memory (KiB), iteration and parallelism costs
+    pub fn argon2id(memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+        PasswordConfig {
+            algorithm: PasswordAlgorithm::Argon2id,
+            argon2_memory_kib: memory_kib,
+            argon2_iterations: iterations,
+            argon2_parallelism: parallelism,
+            ..Default::default()
+        }
+    }
+
+    /// PBKDF2-SHA256 with the given number of rounds
+    pub fn pbkdf2_sha256(rounds: u32) -> Self {
+        PasswordConfig {
+            algorithm: PasswordAlgorithm::Pbkdf2Sha256,
+            pbkdf2_rounds: rounds,
+            ..Default::default()
+        }
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, CryptoError> {
+        let params = argon2::Params::new(
+            self.argon2_memory_kib,
+            self.argon2_iterations,
+            self.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| CryptoError::InvalidInput(format!("Invalid Argon2 parameters: {}", e)))?;
+        Ok(Argon2::new(
+            argon2::Algorithm::Argon2id,
+            Version::V0x13,
+            params,
+        ))
+    }
+}
+
+impl Default for PasswordConfig {
+    /// OWASP recommended minimums: Argon2id m=19 MiB, t=2, p=1; PBKDF2 600,000 rounds
+    fn default() -> Self {
+        PasswordConfig {
+            algorithm: PasswordAlgorithm::Argon2id,
+            argon2_memory_kib: argon2::Params::DEFAULT_M_COST,
+            argon2_iterations: argon2::Params::DEFAULT_T_COST,
+            argon2_parallelism: argon2::Params::DEFAULT_P_COST,
+            pbkdf2_rounds: pbkdf2::Params::RECOMMENDED_ROUNDS as u32,
+        }
+    }
+}
+
+/// Hash a password with the default configuration
+pub fn hash(password: &str) -> Result<String, CryptoError> {
+    hash_with(password, &PasswordConfig::default())
+}
+
+/// Hash a password with the given configuration, returning a PHC string
+pub fn hash_with(password: &str, config: &PasswordConfig) -> Result<String, CryptoError> {
+    hash_with_rng(password, config, &mut OsRandom)
+}
+
+/// Same as [`hash_with`], drawing the salt from `rng`
+pub fn hash_with_rng(
+    password: &str,
+    config: &PasswordConfig,
+    rng: &mut impl RandomSource,
+) -> Result<String, CryptoError> {
+    let salt_bytes = super::generate_random_bytes_with_rng(SALT_LEN, rng)?;
+    let salt = SaltString::encode_b64(&salt_bytes).map_err(op_failed)?;
+
+    let hash = match config.algorithm {
+        PasswordAlgorithm::Argon2id => config
+            .argon2()?
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(op_failed)?,
+        PasswordAlgorithm::Pbkdf2Sha256 => Pbkdf2
+            .hash_password_customized(
+                password.as_bytes(),
+                Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                None,
+                pbkdf2::Params {
+                    rounds: config.pbkdf2_rounds,
+                    output_length: PBKDF2_OUTPUT_LEN,
+                },
+                &salt,
+            )
+            .map_err(op_failed)?,
+    };
+    Ok(hash.to_string())
+}
+
+/// Check `password` against a PHC string in constant time
+///
+/// Returns `Ok(false)` for a wrong password and an error for a malformed or
+/// unsupported hash.
+pub fn verify(password: &str, phc: &str) -> Result<bool, CryptoError> {
+    let parsed = parse(phc)?;
+    algorithm_of(&parsed)?;
+
+    match parsed.verify_password(&[&Argon2::default(), &Pbkdf2], password) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(op_failed(e)),
+    }
+}
+
+/// Whether a hash was created with a different algorithm or cost than `config`
+pub fn needs_rehash(phc: &str, config: &PasswordConfig) -> Result<bool, CryptoError> {
+    let parsed = parse(phc)?;
+    if algorithm_of(&parsed)? != config.algorithm {
+        return Ok(true);
+    }
+
+    let outdated = match config.algorithm {
+        PasswordAlgorithm::Argon2id => {
+            let params = argon2::Params::try_from(&parsed).map_err(invalid_hash)?;
+            parsed.version != Some(Version::V0x13.into())
+                || params.m_cost() != config.argon2_memory_kib
+                || params.t_cost() != config.argon2_iterations
+                || params.p_cost() != config.argon2_parallelism
+        }
+        PasswordAlgorithm::Pbkdf2Sha256 => {
+            let params = pbkdf2::Params::try_from(&parsed).map_err(invalid_hash)?;
+            params.rounds != config.pbkdf2_rounds
+        }
+    };
+    Ok(outdated)
+}
+
+fn parse(phc: &str) -> Result<PasswordHash<'_>, CryptoError> {
+    PasswordHash::new(phc).map_err(invalid_hash)
+}
+
+fn algorithm_of(hash: &PasswordHash<'_>) -> Result<PasswordAlgorithm, CryptoError> {
+    match hash.algorithm.as_str() {
+        "argon2id" => Ok(PasswordAlgorithm::Argon2id),
+        "pbkdf2-sha256" => Ok(PasswordAlgorithm::Pbkdf2Sha256),
+        other => Err(CryptoError::UnsupportedAlgorithm(other.to_string())),
+    }
+}
+
+fn invalid_hash(e: password_hash::Error) -> CryptoError {
+    CryptoError::InvalidInput(format!("Invalid password hash: {}", e))
+}
+
+fn op_failed(e: password_hash::Error) -> CryptoError {
+    CryptoError::OperationFailed(format!("Password hashing failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap settings so tests stay fast in debug builds
+    fn argon2_config() -> PasswordConfig {
+        PasswordConfig::argon2id(64, 1, 1)
+    }
+
+    #[test]
+    fn argon2id_round_trip() {
+        let phc = hash_with("hunter2", &argon2_config()).unwrap();
+        assert!(phc.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert!(verify("hunter2", &phc).unwrap());
+        assert!(!verify("hunter3", &phc).unwrap());
+    }
+
+    #[test]
+    fn pbkdf2_round_trip() {
+        let config = PasswordConfig::pbkdf2_sha256(1_000);
+        let phc = hash_with("hunter2", &config).unwrap();
+        assert!(phc.starts_with("$pbkdf2-sha256$i=1000,l=32$"));
+        assert!(verify("hunter2", &phc).unwrap());
+        assert!(!verify("Hunter2", &phc).unwrap());
+    }
+
+    #[test]
+    fn salts_differ() {
+        let config = argon2_config();
+        assert_ne!(
+            hash_with("same", &config).unwrap(),
+            hash_with("same", &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn rehash_on_cost_or_algorithm_change() {
+        let phc = hash_with("pw", &argon2_config()).unwrap();
+        assert!(!needs_rehash(&phc, &argon2_config()).unwrap());
+        assert!(needs_rehash(&phc, &PasswordConfig::argon2id(128, 1, 1)).unwrap());
+        assert!(needs_rehash(&phc, &PasswordConfig::pbkdf2_sha256(1_000)).unwrap());
+    }
+
+    #[test]
+    fn malformed_hash_is_an_error() {
+        assert!(matches!(
+            verify("pw", "not-a-phc-string"),
+            Err(CryptoError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            verify(
+                "pw",
+                "$scrypt$ln=16,r=8,p=1$aM15713r3Xsvxbi31lqr1Q$nFNh2CVHVjNldFVKDHDlm4CbdRSCdEBsjjJxD+iCs5E"
+            ),
+            Err(CryptoError::UnsupportedAlgorithm(_))
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+//! Pluggable randomness for keys, nonces, salts, tokens and ids.
+//!
+//! Production code uses [`OsRandom`]. Tests that need reproducible output pass
+//! a [`SeededRandom`] to the `*_with_rng` variants instead, e.g.
+//! [`generate_random_string_with_rng`](super::generate_random_string_with_rng).
+
+use rand::{RngCore, SeedableRng, rngs::OsRng};
+use rand_chacha::ChaCha20Rng;
+
+use super::CryptoError;
+
+/// Source of random bytes
+pub trait RandomSource {
+    /// Fill `dest` with random bytes
+    fn fill_random(&mut self, dest: &mut [u8]) -> Result<(), CryptoError>;
+
+    /// Uniformly random `u32`
+    fn next_u32(&mut self) -> Result<u32, CryptoError> {
+        let mut buf = [0u8; 4];
+        self.fill_random(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    /// Uniformly random index in `0..n`, without modulo bias
+    fn index(&mut self, n: usize) -> Result<usize, CryptoError> {
+        let n = u32::try_from(n)
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| CryptoError::InvalidInput(format!("cannot pick from {} items", n)))?;
+        // Reject the top partial range so every index is equally likely
+        let zone = u32::MAX - (u32::MAX - n + 1) % n;
+        loop {
+            let v = self.next_u32()?;
+            if v <= zone {
+                return Ok((v % n) as usize);
+            }
+        }
+    }
+}
+
+impl<R: RandomSource + ?Sized> RandomSource for &mut R {
+    fn fill_random(&mut self, dest: &mut [u8]) -> Result<(), CryptoError> {
+        (**self).fill_random(dest)
+    }
+}
+
+/// Operating system CSPRNG; the default everywhere
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRandom;
+
+impl RandomSource for OsRandom {
+    fn fill_random(&mut self, dest: &mut [u8]) -> Result<(), CryptoError> {
+        OsRng
+            .try_fill_bytes(dest)
+            .map_err(|e| CryptoError::OperationFailed(format!("OS random source failed: {}", e)))
+    }
+}
+
+/// Deterministic ChaCha20 stream for reproducible tests
+///
+/// The same seed always yields the same bytes. Never use it for real keys.
+#[derive(Debug, Clone)]
+pub struct SeededRandom(ChaCha20Rng);
+
+impl SeededRandom {
+    /// Generator for a 32-byte seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        SeededRandom(ChaCha20Rng::from_seed(seed))
+    }
+
+    /// Generator for a small integer seed
+    pub fn from_u64(seed: u64) -> Self {
+        SeededRandom(ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn fill_random(&mut self, dest: &mut [u8]) -> Result<(), CryptoError> {
+        self.0.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Characters used by random string generation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alphabet {
+    chars: Vec<char>,
+}
+
+impl Alphabet {
+    /// `A-Z`, `a-z` and `0-9`
+    pub fn alphanumeric() -> Self {
+        Self::from_chars("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789")
+    }
+
+    /// Alphanumerics plus `-` and `_`, safe in URLs and file names
+    pub fn url_safe() -> Self {
+        Self::from_chars("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_")
+    }
+
+    /// `0-9`, for SMS and e-mail verification codes
+    pub fn digits() -> Self {
+        Self::from_chars("0123456789")
+    }
+
+    /// Alphanumerics without look-alikes `0`/`O`, `1`/`l`/`I`, for codes read by people
+    pub fn unambiguous() -> Self {
+        Self::from_chars("ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789")
+    }
+
+    /// Custom alphabet of at least two distinct characters
+    pub fn custom(chars: &str) -> Result<Self, CryptoError> {
+        let alphabet = Self::from_chars(chars);
+        let mut unique = alphabet.chars.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        if unique.len() != alphabet.chars.len() || unique.len() < 2 {
+            return Err(CryptoError::InvalidInput(
+                "alphabet needs at least two characters and no duplicates".to_string(),
+            ));
+        }
+        Ok(alphabet)
+    }
+
+    /// Characters in the alphabet
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    fn from_chars(chars: &str) -> Self {
+        Alphabet {
+            chars: chars.chars().collect(),
+        }
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self::alphanumeric()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_is_reproducible() {
+        let mut a = SeededRandom::from_u64(42);
+        let mut b = SeededRandom::from_u64(42);
+        let (mut x, mut y) = ([0u8; 16], [0u8; 16]);
+        a.fill_random(&mut x).unwrap();
+        b.fill_random(&mut y).unwrap();
+        assert_eq!(x, y);
+        assert_ne!(x, [0u8; 16]);
+    }
+
+    #[test]
+    fn index_is_in_range_and_covers_all() {
+        let mut rng = SeededRandom::from_u64(7);
+        let mut seen = [false; 10];
+        for _ in 0..500 {
+            seen[rng.index(10).unwrap()] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+        assert!(rng.index(0).is_err());
+    }
+
+    #[test]
+    fn alphabets() {
+        assert!(
+            !Alphabet::unambiguous()
+                .chars()
+                .iter()
+                .any(|c| "0O1lI".contains(*c))
+        );
+        assert_eq!(Alphabet::digits().chars().len(), 10);
+        assert!(Alphabet::custom("aa").is_err());
+        assert!(Alphabet::custom("a").is_err());
+        assert_eq!(Alphabet::custom("ab").unwrap().chars(), &['a', 'b']);
+    }
+}
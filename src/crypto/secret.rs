@@ -0,0 +1,137 @@
+//! Wrapper for sensitive values such as keys, passwords and tokens.
+//!
+//! A [`Secret`] prints as `***` in `Debug` and `Display`, serializes as `"***"`
+//! and zeroes its memory when dropped. The wrapped value can only be read with
+//! [`Secret::expose_secret`], which makes every access easy to find in review.
+//!
+//! To write the real value with serde, opt in per field:
+//!
+//! ```
+//! use cland_rust_share::Secret;
+//!
+//! #[derive(serde::Serialize)]
+//! struct Export {
+//!     #[serde(serialize_with = "cland_rust_share::crypto::secret::serialize_exposed")]
+//!     api_key: Secret<String>,
+//! }
+//! ```
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroize;
+
+/// Placeholder printed and serialized instead of the secret value
+pub const REDACTED: &str = "***";
+
+/// A value that is redacted when printed or serialized and zeroed on drop
+pub struct Secret<T: Zeroize> {
+    inner: T,
+}
+
+impl<T: Zeroize> Secret<T> {
+    /// Wrap a sensitive value
+    pub fn new(value: T) -> Self {
+        Secret { inner: value }
+    }
+
+    /// Borrow the wrapped value
+    pub fn expose_secret(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: Zeroize + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Secret::new(self.inner.clone())
+    }
+}
+
+impl<T: Zeroize + Default> Default for Secret<T> {
+    fn default() -> Self {
+        Secret::new(T::default())
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Secret::new(value.to_string())
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.inner.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T: Zeroize> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T: Zeroize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de, T: Zeroize + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// Serialize the real value; use with `#[serde(serialize_with = "...")]`
+pub fn serialize_exposed<T, S>(secret: &Secret<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Zeroize + Serialize,
+    S: Serializer,
+{
+    secret.expose_secret().serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_everywhere() {
+        let secret = Secret::from("hunter2");
+        assert_eq!(format!("{:?}", secret), "***");
+        assert_eq!(secret.to_string(), "***");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"***\"");
+        assert_eq!(secret.expose_secret(), "hunter2");
+    }
+
+    #[test]
+    fn explicit_expose_mode() {
+        #[derive(Serialize, Deserialize)]
+        struct Export {
+            #[serde(serialize_with = "serialize_exposed")]
+            token: Secret<String>,
+            hidden: Secret<String>,
+        }
+
+        let json = r#"{"token":"abc","hidden":"def"}"#;
+        let export: Export = serde_json::from_str(json).unwrap();
+        assert_eq!(export.hidden.expose_secret(), "def");
+        assert_eq!(
+            serde_json::to_string(&export).unwrap(),
+            r#"{"token":"abc","hidden":"***"}"#
+        );
+    }
+}
@@ -0,0 +1,511 @@
+//! Chunked authenticated encryption for payloads too large to hold in memory.
+//!
+//! Uses the STREAM construction: the plaintext is split into fixed-size
+//! chunks, each sealed with the keyring's algorithm under the nonce
+//!
+//! ```text
+//! nonce_prefix (7) | chunk counter:u32 BE | last-chunk flag:u8
+//! ```
+//!
+//! so chunks cannot be reordered, and a stream cut at a chunk boundary fails
+//! because its final chunk was not sealed with the last-chunk flag. The header
+//! below is authenticated as associated data of every chunk:
+//!
+//! ```text
+//! magic "CLSE" | version:u8 | algorithm:u8 | chunk_size:u32 BE | kid_len:u8 | kid | nonce_prefix (7)
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::{
+    Algorithm, CryptoError, Keyring, OsRandom, RandomSource, Secret,
+    aes::{KEY_LEN, NONCE_LEN, TAG_LEN},
+};
+use crate::CommonError;
+
+/// Leading bytes of every encrypted stream
+pub const STREAM_MAGIC: &[u8; 4] = b"CLSE";
+
+/// Current stream format version
+pub const STREAM_VERSION: u8 = 1;
+
+/// Default plaintext bytes per chunk
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Largest chunk size accepted, bounding memory use when decrypting
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Random part of the per-chunk nonce
+const NONCE_PREFIX_LEN: usize = NONCE_LEN - 5;
+
+/// Encrypts everything written to it into `inner`
+///
+/// Call [`finish`](Self::finish) when done; a stream dropped without it is
+/// missing its final chunk and will be rejected when decrypted.
+pub struct StreamEncryptor<W: Write> {
+    inner: W,
+    state: ChunkState,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> StreamEncryptor<W> {
+    /// Start a stream with the keyring's primary key and the default chunk size
+    pub fn new(keyring: &Keyring, inner: W) -> Result<Self, CommonError> {
+        Self::with_chunk_size(keyring, inner, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Start a stream with `chunk_size` plaintext bytes per chunk
+    pub fn with_chunk_size(
+        keyring: &Keyring,
+        inner: W,
+        chunk_size: usize,
+    ) -> Result<Self, CommonError> {
+        Self::with_rng(keyring, inner, chunk_size, &mut OsRandom)
+    }
+
+    /// Same as [`with_chunk_size`](Self::with_chunk_size), drawing the nonce prefix from `rng`
+    pub fn with_rng(
+        keyring: &Keyring,
+        mut inner: W,
+        chunk_size: usize,
+        rng: &mut impl RandomSource,
+    ) -> Result<Self, CommonError> {
+        validate_chunk_size(chunk_size)?;
+        let (kid, key) = keyring.primary();
+
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        rng.fill_random(&mut nonce_prefix)?;
+
+        let mut header = Vec::with_capacity(18 + kid.len());
+        header.extend_from_slice(STREAM_MAGIC);
+        header.push(STREAM_VERSION);
+        header.push(keyring.algorithm().id());
+        header.extend_from_slice(&(chunk_size as u32).to_be_bytes());
+        header.push(kid.len() as u8);
+        header.extend_from_slice(kid.as_bytes());
+        header.extend_from_slice(&nonce_prefix);
+        inner.write_all(&header)?;
+
+        Ok(StreamEncryptor {
+            inner,
+            state: ChunkState {
+                algorithm: keyring.algorithm(),
+                key: Secret::new(*key),
+                nonce_prefix,
+                header,
+                chunk_size,
+                counter: 0,
+            },
+            buf: Vec::with_capacity(chunk_size),
+        })
+    }
+
+    /// Seal the final chunk and return the inner writer
+    pub fn finish(mut self) -> Result<W, CommonError> {
+        let sealed = self.state.seal(&self.buf, true)?;
+        self.inner.write_all(&sealed)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for StreamEncryptor<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < data.len() {
+            // Only seal a full chunk once more data arrives, so that the last
+            // chunk is always sealed by `finish`
+            if self.buf.len() == self.state.chunk_size {
+                let sealed = self.state.seal(&self.buf, false).map_err(invalid_data)?;
+                self.inner.write_all(&sealed)?;
+                self.buf.clear();
+            }
+            let take = (self.state.chunk_size - self.buf.len()).min(data.len() - written);
+            self.buf.extend_from_slice(&data[written..written + take]);
+            written += take;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a stream produced by [`StreamEncryptor`] while it is read
+///
+/// Read errors caused by tampering, truncation or reordering have kind
+/// [`io::ErrorKind::InvalidData`] and wrap a [`CryptoError`].
+pub struct StreamDecryptor<R: Read> {
+    inner: R,
+    state: ChunkState,
+    pending: Vec<u8>,
+    plain: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> StreamDecryptor<R> {
+    /// Read the stream header and look up its key in `keyring`
+    pub fn new(keyring: &Keyring, mut inner: R) -> Result<Self, CommonError> {
+        let mut fixed = [0u8; 11];
+        read_header_part(&mut inner, &mut fixed)?;
+        if &fixed[..4] != STREAM_MAGIC {
+            return Err(CryptoError::InvalidInput("not an encrypted stream".to_string()).into());
+        }
+        if fixed[4] != STREAM_VERSION {
+            return Err(CryptoError::UnsupportedVersion(fixed[4]).into());
+        }
+        let algorithm = Algorithm::from_id(fixed[5])?;
+        let chunk_size = u32::from_be_bytes(fixed[6..10].try_into().expect("4 bytes")) as usize;
+        validate_chunk_size(chunk_size)?;
+
+        let mut rest = vec![0u8; fixed[10] as usize + NONCE_PREFIX_LEN];
+        read_header_part(&mut inner, &mut rest)?;
+        let (kid, prefix) = rest.split_at(fixed[10] as usize);
+        let kid = std::str::from_utf8(kid)
+            .map_err(|_| CryptoError::InvalidInput("key id is not valid UTF-8".to_string()))?;
+        let key = keyring.require(kid)?;
+
+        let mut header = fixed.to_vec();
+        header.extend_from_slice(&rest);
+        Ok(StreamDecryptor {
+            inner,
+            state: ChunkState {
+                algorithm,
+                key: Secret::new(*key),
+                nonce_prefix: prefix.try_into().expect("prefix length"),
+                header,
+                chunk_size,
+                counter: 0,
+            },
+            pending: Vec::with_capacity(chunk_size + TAG_LEN + 1),
+            plain: Vec::new(),
+            pos: 0,
+            done: false,
+        })
+    }
+
+    /// Decrypt the next chunk into `plain`
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let sealed_len = self.state.chunk_size + TAG_LEN;
+        // Read one byte past a full chunk to learn whether it is the last one
+        while self.pending.len() <= sealed_len {
+            let start = self.pending.len();
+            self.pending.resize(sealed_len + 1, 0);
+            match self.inner.read(&mut self.pending[start..]) {
+                Ok(0) => {
+                    self.pending.truncate(start);
+                    break;
+                }
+                Ok(n) => self.pending.truncate(start + n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => self.pending.truncate(start),
+                Err(e) => {
+                    self.pending.truncate(start);
+                    return Err(e);
+                }
+            }
+        }
+
+        let last = self.pending.len() <= sealed_len;
+        if last && self.pending.len() < TAG_LEN {
+            return Err(invalid_data(CryptoError::InvalidInput(
+                "encrypted stream is truncated".to_string(),
+            )));
+        }
+        let take = self.pending.len().min(sealed_len);
+        self.plain = self
+            .state
+            .open(&self.pending[..take], last)
+            .map_err(invalid_data)?;
+        self.pending.drain(..take);
+        self.pos = 0;
+        self.done = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for StreamDecryptor<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+        let n = out.len().min(self.plain.len() - self.pos);
+        out[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Encrypt everything from `reader` into `writer`, returning the plaintext length
+pub fn encrypt_stream(
+    keyring: &Keyring,
+    mut reader: impl Read,
+    writer: impl Write,
+) -> Result<u64, CommonError> {
+    let mut encryptor = StreamEncryptor::new(keyring, writer)?;
+    let copied = io::copy(&mut reader, &mut encryptor).map_err(from_io)?;
+    encryptor.finish()?;
+    Ok(copied)
+}
+
+/// Decrypt a stream from `reader` into `writer`, returning the plaintext length
+pub fn decrypt_stream(
+    keyring: &Keyring,
+    reader: impl Read,
+    mut writer: impl Write,
+) -> Result<u64, CommonError> {
+    let mut decryptor = StreamDecryptor::new(keyring, reader)?;
+    let copied = io::copy(&mut decryptor, &mut writer).map_err(from_io)?;
+    writer.flush()?;
+    Ok(copied)
+}
+
+/// Encrypt the file at `src` into `dst`
+pub fn encrypt_file(
+    keyring: &Keyring,
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> Result<u64, CommonError> {
+    let reader = BufReader::new(File::open(src)?);
+    write_atomically(dst.as_ref(), |writer| {
+        encrypt_stream(keyring, reader, writer)
+    })
+}
+
+/// Decrypt the file at `src` into `dst`
+///
+/// `dst` is only created once the whole stream has been authenticated.
+pub fn decrypt_file(
+    keyring: &Keyring,
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> Result<u64, CommonError> {
+    let reader = BufReader::new(File::open(src)?);
+    write_atomically(dst.as_ref(), |writer| {
+        decrypt_stream(keyring, reader, writer)
+    })
+}
+
+/// Write to a new temporary file next to `dst` and rename it on success
+///
+/// The temporary name has a random suffix and is created with `create_new`,
+/// so concurrent writers never share it and leftovers from a crash are never
+/// reused.
+fn write_atomically(
+    dst: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<u64, CommonError>,
+) -> Result<u64, CommonError> {
+    let (tmp, file) = create_temp_file(dst)?;
+
+    let mut writer = BufWriter::new(file);
+    let result = write(&mut writer)
+        .and_then(|n| {
+            writer
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .sync_all()?;
+            Ok(n)
+        })
+        .and_then(|n| {
+            fs::rename(&tmp, dst)?;
+            Ok(n)
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Create `.<name>.<random>.partial` in the directory of `dst`
+fn create_temp_file(dst: &Path) -> Result<(PathBuf, File), CommonError> {
+    let name = dst.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "destination has no file name")
+    })?;
+    let mut attempts = 0;
+    loop {
+        let mut suffix = [0u8; 8];
+        OsRandom.fill_random(&mut suffix)?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(format!(".{}.partial", super::encoding::hex_encode(suffix)));
+        let tmp = dst.with_file_name(tmp_name);
+        match File::options().write(true).create_new(true).open(&tmp) {
+            Ok(file) => return Ok((tmp, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 3 => attempts += 1,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Key, nonce and header shared by every chunk of one stream
+struct ChunkState {
+    algorithm: Algorithm,
+    key: Secret<[u8; KEY_LEN]>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    header: Vec<u8>,
+    chunk_size: usize,
+    counter: u32,
+}
+
+impl ChunkState {
+    fn seal(&mut self, chunk: &[u8], last: bool) -> Result<Vec<u8>, CryptoError> {
+        let nonce = self.next_nonce(last)?;
+        self.algorithm
+            .encrypt(self.key.expose_secret(), &nonce, chunk, &self.header)
+    }
+
+    fn open(&mut self, sealed: &[u8], last: bool) -> Result<Vec<u8>, CryptoError> {
+        let index = self.counter;
+        let nonce = self.next_nonce(last)?;
+        self.algorithm
+            .decrypt(self.key.expose_secret(), &nonce, sealed, &self.header)
+            .map_err(|_| {
+                CryptoError::OperationFailed(format!(
+                    "stream chunk {} failed authentication (tampered, reordered or truncated)",
+                    index
+                ))
+            })
+    }
+
+    fn next_nonce(&mut self, last: bool) -> Result<[u8; NONCE_LEN], CryptoError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[NONCE_LEN - 1] = last as u8;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| CryptoError::OperationFailed("stream too long".to_string()))?;
+        Ok(nonce)
+    }
+}
+
+fn validate_chunk_size(chunk_size: usize) -> Result<(), CryptoError> {
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(CryptoError::InvalidInput(format!(
+            "chunk size must be 1..={} bytes, got {}",
+            MAX_CHUNK_SIZE, chunk_size
+        )));
+    }
+    Ok(())
+}
+
+fn read_header_part(reader: &mut impl Read, buf: &mut [u8]) -> Result<(), CommonError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            CryptoError::InvalidInput("encrypted stream header is truncated".to_string()).into()
+        }
+        _ => CommonError::Io(e),
+    })
+}
+
+fn invalid_data(err: CryptoError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Turn crypto failures carried through `io::Error` back into `CommonError::Crypto`
+fn from_io(err: io::Error) -> CommonError {
+    if err.get_ref().is_some_and(|inner| inner.is::<CryptoError>()) {
+        let inner = err.into_inner().expect("checked above");
+        let crypto = inner.downcast::<CryptoError>().expect("checked above");
+        return CommonError::Crypto(*crypto);
+    }
+    CommonError::Io(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring() -> Keyring {
+        Keyring::new("k1", [9u8; KEY_LEN]).unwrap()
+    }
+
+    fn encrypt(data: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut enc = StreamEncryptor::with_chunk_size(&keyring(), Vec::new(), chunk_size).unwrap();
+        enc.write_all(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    fn decrypt(data: &[u8]) -> Result<Vec<u8>, CommonError> {
+        let mut out = Vec::new();
+        decrypt_stream(&keyring(), data, &mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn round_trip_across_chunk_boundaries() {
+        for len in [0, 1, 15, 16, 17, 48, 100] {
+            let data: Vec<u8> = (0..len as u8).collect();
+            assert_eq!(decrypt(&encrypt(&data, 16)).unwrap(), data, "len {}", len);
+        }
+    }
+
+    #[test]
+    fn truncation_is_detected() {
+        let sealed = encrypt(&[1u8; 64], 16);
+        let header_len = 11 + 2 + NONCE_PREFIX_LEN;
+        // Drop the final chunk entirely, leaving a stream that ends on a boundary
+        let cut = sealed.len() - (16 + TAG_LEN);
+        assert_eq!((cut - header_len) % (16 + TAG_LEN), 0);
+        assert!(matches!(
+            decrypt(&sealed[..cut]),
+            Err(CommonError::Crypto(_))
+        ));
+        assert!(matches!(
+            decrypt(&sealed[..header_len]),
+            Err(CommonError::Crypto(_))
+        ));
+    }
+
+    #[test]
+    fn reordering_is_detected() {
+        let sealed = encrypt(&[1u8; 48], 16);
+        let header_len = 11 + 2 + NONCE_PREFIX_LEN;
+        let chunk = 16 + TAG_LEN;
+        let mut swapped = sealed[..header_len].to_vec();
+        swapped.extend_from_slice(&sealed[header_len + chunk..header_len + 2 * chunk]);
+        swapped.extend_from_slice(&sealed[header_len..header_len + chunk]);
+        swapped.extend_from_slice(&sealed[header_len + 2 * chunk..]);
+        let err = decrypt(&swapped).unwrap_err();
+        assert!(err.to_string().contains("chunk 0"));
+    }
+
+    #[test]
+    fn header_is_authenticated() {
+        let mut sealed = encrypt(b"hello", 16);
+        sealed[9] ^= 1; // chunk size
+        assert!(decrypt(&sealed).is_err());
+    }
+
+    #[test]
+    fn temp_files_are_unique_and_cleaned_up() {
+        let dir = std::env::temp_dir().join(format!("stream-tmp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dst = dir.join("out.bin");
+
+        let (a, _) = create_temp_file(&dst).unwrap();
+        let (b, _) = create_temp_file(&dst).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), dst.parent());
+
+        let err = write_atomically(&dst, |_| Err(CommonError::crypto("boom"))).unwrap_err();
+        assert!(matches!(err, CommonError::Crypto(_)));
+        assert!(!dst.exists());
+        assert_eq!(
+            write_atomically(&dst, |w| Ok(w.write(b"ok")? as u64)).unwrap(),
+            2
+        );
+        assert_eq!(fs::read(&dst).unwrap(), b"ok");
+
+        // Only the two temp files created directly above are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
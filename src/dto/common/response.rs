@@ -5,12 +5,14 @@ use serde_json::Value;
 #[cfg(feature = "utoipa_support")]
 use utoipa::ToSchema;
 
-use crate::ErrorCode;
+use crate::utils::http_code::StringCode;
+use crate::{CommonError, ErrorCode, FieldError, StructuredCode};
 
 /// 全局统一 API 响应体格式
 ///
 /// 格式说明：
-/// - code: 业务状态码（字符串类型，200=成功）
+/// - code: 业务状态码（序列化为整型；200=成功）。需要字符串形式的调用方使用
+///   `ApiResponse<T, StringCode>`（见 [`ApiResponse::with_string_code`]），按次选择，不影响其他调用方
 /// - msg: 响应描述信息
 /// - data: 响应数据（可选，失败时可省略）
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -21,19 +23,16 @@ use crate::ErrorCode;
     feature = "utoipa_support",
     schema(
         example = json!({
-            "code": "200",
+            "code": 200,
             "msg": "Success",
             "data": { "user_id": "123e4567-e89b-12d3-a456-426614174000", "username": "example" }
         })
     )
 )]
-pub struct ApiResponse<T> {
+pub struct ApiResponse<T, C = StructuredCode> {
     /// 业务状态码（200=成功，常见错误码：40010010001=参数错误、50020030002=系统错误）
-    #[cfg_attr(
-        feature = "utoipa_support",
-        schema(example = "200")  // 仅保留支持的 example 属性
-    )]
-    pub code: String,
+    #[cfg_attr(feature = "utoipa_support", schema(inline))]
+    pub code: C,
 
     /// 响应描述信息（成功时为 Success，错误时为具体原因）
     #[cfg_attr(
@@ -51,12 +50,11 @@ pub struct ApiResponse<T> {
     pub data: Option<T>,
 }
 
-// 原有业务逻辑完全不变（以下代码无修改）
 impl<T> ApiResponse<T> {
     /// Create a success response with data
     pub fn success(data: T) -> Self {
         ApiResponse {
-            code: "200".to_string(),
+            code: ErrorCode::Ok.into(),
             msg: "Success".to_string(),
             data: Some(data),
         }
@@ -65,14 +63,14 @@ impl<T> ApiResponse<T> {
     /// Create a success response without data
     pub fn ok() -> Self {
         ApiResponse {
-            code: "200".to_string(),
+            code: ErrorCode::Ok.into(),
             msg: "Success".to_string(),
             data: None,
         }
     }
 
     /// Create an error response with code and message
-    pub fn error(code: impl Into<String>, msg: impl Into<String>) -> Self {
+    pub fn error(code: impl Into<StructuredCode>, msg: impl Into<String>) -> Self {
         ApiResponse {
             code: code.into(),
             msg: msg.into(),
@@ -81,34 +79,69 @@ impl<T> ApiResponse<T> {
     }
 
     /// Create an error response with data
-    pub fn error_with_data(code: impl Into<String>, msg: impl Into<String>, data: T) -> Self {
+    pub fn error_with_data(
+        code: impl Into<StructuredCode>,
+        msg: impl Into<String>,
+        data: T,
+    ) -> Self {
         ApiResponse {
             code: code.into(),
             msg: msg.into(),
             data: Some(data),
         }
     }
+
+    /// Same response with `code` serialized as a string, for clients that
+    /// cannot take the integer form
+    pub fn with_string_code(self) -> ApiResponse<T, StringCode> {
+        ApiResponse {
+            code: StringCode(self.code),
+            msg: self.msg,
+            data: self.data,
+        }
+    }
+}
+
+/// 由错误生成响应：code 与 msg 取自 [`CommonError::code`] 和
+/// [`CommonError::user_message`]，内部细节不会返回给客户端
+impl From<CommonError> for ApiResponse<()> {
+    fn from(err: CommonError) -> Self {
+        ApiResponse::error(err.code(), err.user_message())
+    }
+}
+
+/// 同上，字段级错误放入 data（无字段时省略 data）
+impl From<CommonError> for ApiResponse<Vec<FieldError>> {
+    fn from(err: CommonError) -> Self {
+        let fields = err.fields().to_vec();
+        ApiResponse {
+            code: err.code(),
+            msg: err.user_message().to_string(),
+            data: (!fields.is_empty()).then_some(fields),
+        }
+    }
 }
 
 /// 通用 API JSON envelope 构造器
 ///
 /// 格式:
 /// {
-///   "code": <string>,
+///   "code": <integer|string>,
 ///   "msg": "<string>",
 ///   "data": <any|null>
 /// }
 pub fn envelope<T: Serialize>(
-    code: impl AsRef<str>,
+    code: impl Into<StructuredCode>,
     msg: impl AsRef<str>,
     data: Option<&T>,
 ) -> Value {
+    let code: StructuredCode = code.into();
     let data_value = match data {
         Some(d) => serde_json::to_value(d).unwrap_or(Value::Null),
         None => Value::Null,
     };
     serde_json::json!({
-        "code": code.as_ref(),
+        "code": code,
         "msg": msg.as_ref(),
         "data": data_value
     })
@@ -116,7 +149,7 @@ pub fn envelope<T: Serialize>(
 
 /// 快捷构造器
 pub fn ok<T: Serialize>(data: &T) -> Value {
-    envelope(ErrorCode::Ok.value().to_string(), "", Some(data))
+    envelope(ErrorCode::Ok, "", Some(data))
 }
 
 /// 简化的对外 API：只提供三个常用构造器
@@ -125,45 +158,107 @@ pub fn ok<T: Serialize>(data: &T) -> Value {
 /// - `param_error`：参数错误，使用文档示例的参数错误码 40010010001
 /// - `system_error`：系统错误，使用文档示例的系统错误码 50020030002
 pub fn success<T: Serialize>(data: &T) -> Value {
-    envelope(ErrorCode::Ok.value().to_string(), "Success", Some(data))
+    envelope(ErrorCode::Ok, "Success", Some(data))
 }
 
 pub fn param_error(msg: &str) -> Value {
-    envelope::<()>(ErrorCode::BadRequest.value().to_string(), msg, None::<&()>)
+    envelope::<()>(ErrorCode::BadRequest, msg, None::<&()>)
 }
 
 pub fn system_error(msg: &str) -> Value {
-    envelope::<()>(ErrorCode::Internal.value().to_string(), msg, None::<&()>)
+    envelope::<()>(ErrorCode::Internal, msg, None::<&()>)
 }
 
 /// 无 data 的错误/提示
-pub fn error(code: impl AsRef<str>, msg: &str) -> Value {
+pub fn error(code: impl Into<StructuredCode>, msg: &str) -> Value {
     envelope::<()>(code, msg, None::<&()>)
 }
 
 /// 带 data 的错误
-pub fn error_data<T: Serialize>(code: impl AsRef<str>, msg: &str, data: &T) -> Value {
+pub fn error_data<T: Serialize>(code: impl Into<StructuredCode>, msg: &str, data: &T) -> Value {
     envelope(code, msg, Some(data))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse_code;
 
     #[test]
     fn serialize_success_with_data() {
         let r = ApiResponse::success(serde_json::json!({ "user_id": 123, "username": "example" }));
         let s = serde_json::to_string(&r).unwrap();
-        assert!(s.contains("\"code\":\"200\""));
+        assert!(s.contains("\"code\":200"));
         assert!(s.contains("\"user_id\":123"));
     }
 
     #[test]
     fn serialize_error() {
-        let r: ApiResponse<()> =
-            ApiResponse::error("40010010001", "Invalid parameter: user_id is missing");
+        let r: ApiResponse<()> = ApiResponse::error(
+            parse_code(40010010001).unwrap(),
+            "Invalid parameter: user_id is missing",
+        );
         let s = serde_json::to_string(&r).unwrap();
-        assert!(s.contains("\"code\":\"40010010001\""));
+        assert!(s.contains("\"code\":40010010001"));
         assert!(s.contains("Invalid parameter"));
     }
+
+    #[test]
+    fn serialize_code_as_string() {
+        let r: ApiResponse<()> = ApiResponse::error(parse_code(40010010001).unwrap(), "bad");
+        let s = serde_json::to_string(&r.clone().with_string_code()).unwrap();
+        assert!(s.contains("\"code\":\"40010010001\""));
+        assert!(
+            serde_json::to_string(&r)
+                .unwrap()
+                .contains("\"code\":40010010001")
+        );
+
+        let ok = serde_json::to_value(ApiResponse::<()>::ok().with_string_code()).unwrap();
+        assert_eq!(ok["code"], "200");
+        let back: ApiResponse<(), StringCode> = serde_json::from_value(ok).unwrap();
+        assert_eq!(back.code, StringCode::from(ErrorCode::Ok));
+    }
+
+    #[test]
+    fn deserialize_either_form() {
+        let a: ApiResponse<()> = serde_json::from_str(r#"{"code":40010010001,"msg":""}"#).unwrap();
+        let b: ApiResponse<()> =
+            serde_json::from_str(r#"{"code":"40010010001","msg":""}"#).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.code.system, 1001);
+        assert!(serde_json::from_str::<ApiResponse<()>>(r#"{"code":"20x","msg":""}"#).is_err());
+        assert!(serde_json::from_str::<ApiResponse<()>>(r#"{"code":201,"msg":""}"#).is_err());
+    }
+
+    #[test]
+    fn from_common_error_hides_internal_detail() {
+        let err = CommonError::new(ErrorCode::UnprocessableEntity, "invalid user")
+            .with_detail("row 7 rejected by constraint users_email_key")
+            .with_field("email", "already registered");
+        let r: ApiResponse<Vec<FieldError>> = err.into();
+        assert_eq!(r.code, ErrorCode::UnprocessableEntity.as_structured());
+        assert_eq!(r.msg, "invalid user");
+        let json = serde_json::to_value(&r).unwrap();
+        assert_eq!(json["data"][0]["error_field"], "email");
+        assert!(!json.to_string().contains("constraint"));
+
+        let r: ApiResponse<()> = CommonError::Other("db down".into()).into();
+        assert_eq!(r.code, ErrorCode::Internal.as_structured());
+        assert_eq!(r.msg, crate::error::INTERNAL_ERROR_MESSAGE);
+    }
+
+    #[cfg(feature = "utoipa_support")]
+    #[test]
+    fn schema_matches_code_format() {
+        use utoipa::PartialSchema;
+
+        let schema = serde_json::to_value(ApiResponse::<String>::schema()).unwrap();
+        let code = &schema["properties"]["code"]["oneOf"][0];
+        assert_eq!(code["type"], "integer", "{}", code);
+
+        let schema = serde_json::to_value(ApiResponse::<String, StringCode>::schema()).unwrap();
+        let code = &schema["properties"]["code"]["oneOf"][0];
+        assert_eq!(code["type"], "string", "{}", code);
+    }
 }
@@ -1,19 +1,138 @@
+use std::error::Error as StdError;
+use std::fmt;
+
 use thiserror::Error;
 
+use crate::crypto::CryptoError;
+use crate::crypto::hmac::SignatureCode;
+use crate::utils::error_codes::{ErrorCodeRegistry, RegistryError};
+use crate::utils::validation_errors::FieldError;
+use crate::utils::{ErrorCode, StructuredCode};
+
+crate::define_error_codes! {
+    /// Structured codes for token validation failures
+    pub enum TokenCode: 9001 {
+        Expired(401, 101) => "token expired" { "zh-CN": "令牌已过期" },
+        NotYetValid(401, 102) => "token not yet valid" { "zh-CN": "令牌尚未生效" },
+        Invalid(401, 103) => "invalid token" { "zh-CN": "令牌无效" },
+        InvalidClaim(401, 104) => "invalid token claim" { "zh-CN": "令牌声明无效" },
+    }
+}
+
+/// System id used in structured codes for token validation failures
+pub const TOKEN_SYSTEM: i32 = TokenCode::SYSTEM;
+
+/// Registry of every code set declared by this crate
+///
+/// Applications register their own sets on top of it to catch clashes with
+/// the crate's codes.
+pub fn builtin_codes() -> Result<ErrorCodeRegistry, RegistryError> {
+    ErrorCodeRegistry::new()
+        .register::<SignatureCode>()?
+        .register::<TokenCode>()
+}
+
+/// Message shown to clients for errors whose details must stay internal
+pub const INTERNAL_ERROR_MESSAGE: &str = "internal server error";
+
 /// Unified error type used across the crate
+///
+/// Every error has a [`code`](Self::code), a [`user_message`](Self::user_message)
+/// that is safe to return to clients, and a `Display`/[`source`](StdError::source)
+/// chain with the internal detail for logs. Use [`CommonError::new`] for
+/// errors with an explicit code, and [`ResultExt::context`] to say what was
+/// being done when an error happened.
 #[derive(Debug, Error)]
 pub enum CommonError {
     #[error("config error: {0}")]
     Config(String),
 
     #[error("crypto error: {0}")]
-    Crypto(String),
+    Crypto(#[from] CryptoError),
 
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 
     #[error("other: {0}")]
     Other(String),
+
+    #[error("token expired")]
+    TokenExpired,
+
+    #[error("token not yet valid")]
+    TokenNotYetValid,
+
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+
+    #[error("invalid token claim: {0}")]
+    InvalidClaim(String),
+
+    /// Error with an explicit structured code and user-facing message
+    #[error(transparent)]
+    Coded(Box<CodedError>),
+
+    /// What was being done when the wrapped error happened
+    #[error(transparent)]
+    Context(ContextError),
+}
+
+/// Payload of [`CommonError::Coded`]
+#[derive(Debug)]
+pub struct CodedError {
+    code: StructuredCode,
+    message: String,
+    detail: Option<String>,
+    fields: Vec<FieldError>,
+    source: Option<Box<dyn StdError + Send + Sync>>,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.message, detail),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl StdError for CodedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|e| e as &(dyn StdError + 'static))
+    }
+}
+
+/// Payload of [`CommonError::Context`]; `source()` is the wrapped error
+#[derive(Debug)]
+pub struct ContextError {
+    context: String,
+    source: Box<CommonError>,
+}
+
+impl ContextError {
+    /// What was being done, e.g. `"loading user"`
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    /// The wrapped error
+    pub fn inner(&self) -> &CommonError {
+        &self.source
+    }
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.context)
+    }
+}
+
+impl StdError for ContextError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.source)
+    }
 }
 
 impl CommonError {
@@ -22,7 +141,162 @@ impl CommonError {
     }
 
     pub fn crypto(msg: impl Into<String>) -> Self {
-        CommonError::Crypto(msg.into())
+        CommonError::Crypto(CryptoError::Other(msg.into()))
+    }
+
+    /// Error with `code` and a message that is safe to show to clients
+    pub fn new(code: impl Into<StructuredCode>, message: impl Into<String>) -> Self {
+        CommonError::Coded(Box::new(CodedError {
+            code: code.into(),
+            message: message.into(),
+            detail: None,
+            fields: Vec::new(),
+            source: None,
+        }))
+    }
+
+    /// `500` error wrapping `source`; clients only see [`INTERNAL_ERROR_MESSAGE`]
+    pub fn internal(source: impl StdError + Send + Sync + 'static) -> Self {
+        CommonError::new(ErrorCode::Internal, INTERNAL_ERROR_MESSAGE).with_source(source)
+    }
+
+    /// Attach internal detail for logs; never shown to clients
+    pub fn with_detail(self, detail: impl Into<String>) -> Self {
+        self.map_coded(|c| c.detail = Some(detail.into()))
+    }
+
+    /// Attach a field-level detail without a rule code
+    pub fn with_field(self, field: impl Into<String>, detail: impl Into<String>) -> Self {
+        self.with_field_error(FieldError {
+            field: field.into(),
+            code: None,
+            message: detail.into().into(),
+        })
+    }
+
+    /// Attach a field error, e.g. one recorded by [`ValidationErrors`](crate::ValidationErrors)
+    pub fn with_field_error(self, error: FieldError) -> Self {
+        self.map_coded(|c| c.fields.push(error))
+    }
+
+    /// Record the underlying cause, returned by [`source`](StdError::source)
+    pub fn with_source(self, source: impl StdError + Send + Sync + 'static) -> Self {
+        self.map_coded(|c| c.source = Some(Box::new(source)))
+    }
+
+    /// Wrap with what was being done, e.g. `"loading user"`
+    pub fn context(self, context: impl Into<String>) -> Self {
+        CommonError::Context(ContextError {
+            context: context.into(),
+            source: Box::new(self),
+        })
+    }
+
+    /// Structured code for API responses: token failures are `401` with
+    /// system [`TOKEN_SYSTEM`], coded errors use their own code, everything
+    /// else is a plain `500`.
+    pub fn code(&self) -> StructuredCode {
+        match self {
+            CommonError::TokenExpired => TokenCode::Expired.into(),
+            CommonError::TokenNotYetValid => TokenCode::NotYetValid.into(),
+            CommonError::InvalidToken(_) => TokenCode::Invalid.into(),
+            CommonError::InvalidClaim(_) => TokenCode::InvalidClaim.into(),
+            CommonError::Coded(c) => c.code,
+            CommonError::Context(c) => c.source.code(),
+            _ => ErrorCode::Internal.as_structured(),
+        }
+    }
+
+    /// HTTP status to send, derived from [`code`](Self::code)
+    pub fn http_status(&self) -> u16 {
+        self.code().http_status()
+    }
+
+    /// Message that is safe to return to clients
+    pub fn user_message(&self) -> &str {
+        match self {
+            CommonError::TokenExpired => TokenCode::Expired.message(),
+            CommonError::TokenNotYetValid => TokenCode::NotYetValid.message(),
+            CommonError::InvalidToken(_) => TokenCode::Invalid.message(),
+            CommonError::InvalidClaim(_) => TokenCode::InvalidClaim.message(),
+            CommonError::Coded(c) => &c.message,
+            CommonError::Context(c) => c.source.user_message(),
+            _ => INTERNAL_ERROR_MESSAGE,
+        }
+    }
+
+    /// Internal detail for logs: this error and every cause, joined by `": "`
+    ///
+    /// A cause whose text already ends the chain is skipped, since `#[from]`
+    /// variants print their source in their own message.
+    pub fn detail(&self) -> String {
+        let mut out = self.to_string();
+        let mut source = self.source();
+        while let Some(err) = source {
+            let text = err.to_string();
+            if !out.ends_with(&text) {
+                out.push_str(": ");
+                out.push_str(&text);
+            }
+            source = err.source();
+        }
+        out
+    }
+
+    /// Field-level details, empty unless added with [`with_field`](Self::with_field)
+    /// or [`with_field_error`](Self::with_field_error)
+    pub fn fields(&self) -> &[FieldError] {
+        match self {
+            CommonError::Coded(c) => &c.fields,
+            CommonError::Context(c) => c.source.fields(),
+            _ => &[],
+        }
+    }
+
+    fn map_coded(self, f: impl FnOnce(&mut CodedError)) -> Self {
+        match self {
+            CommonError::Coded(mut c) => {
+                f(&mut c);
+                CommonError::Coded(c)
+            }
+            CommonError::Context(c) => CommonError::Context(ContextError {
+                context: c.context,
+                source: Box::new(c.source.map_coded(f)),
+            }),
+            other => {
+                let mut coded = CodedError {
+                    code: other.code(),
+                    message: other.user_message().to_string(),
+                    detail: None,
+                    fields: Vec::new(),
+                    source: None,
+                };
+                f(&mut coded);
+                if coded.source.is_none() {
+                    coded.source = Some(Box::new(other));
+                }
+                CommonError::Coded(Box::new(coded))
+            }
+        }
+    }
+}
+
+/// `.context()` on any `Result` whose error converts into [`CommonError`]
+pub trait ResultExt<T> {
+    /// Wrap the error with what was being done, e.g. `"loading user"`
+    fn context(self, context: impl Into<String>) -> Result<T, CommonError>;
+
+    /// Like [`context`](Self::context), building the text only on error
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> Result<T, CommonError>;
+}
+
+impl<T, E: Into<CommonError>> ResultExt<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T, CommonError> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> Result<T, CommonError> {
+        self.map_err(|e| e.into().context(f()))
     }
 }
 
@@ -32,8 +306,57 @@ impl From<std::string::String> for CommonError {
     }
 }
 
-impl From<crate::crypto::CryptoError> for CommonError {
-    fn from(err: crate::crypto::CryptoError) -> Self {
-        CommonError::Crypto(err.to_string())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coded_error_keeps_detail_out_of_user_message() {
+        let err = CommonError::new(ErrorCode::NotFound, "user not found")
+            .with_detail("id 42 missing in shard 3")
+            .with_field("user_id", "does not exist");
+        assert_eq!(err.code(), ErrorCode::NotFound.as_structured());
+        assert_eq!(err.user_message(), "user not found");
+        assert_eq!(err.to_string(), "user not found: id 42 missing in shard 3");
+        assert_eq!(err.fields()[0].field, "user_id");
+    }
+
+    #[test]
+    fn context_chain() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let err = Err::<(), _>(io).context("loading user").unwrap_err();
+        assert_eq!(err.to_string(), "loading user");
+        assert_eq!(err.detail(), "loading user: io error: no such file");
+
+        let coded = CommonError::new(ErrorCode::BadGateway, "upstream failed")
+            .with_source(std::io::Error::other("connection reset"))
+            .context("calling billing");
+        assert_eq!(
+            coded.detail(),
+            "calling billing: upstream failed: connection reset"
+        );
+        assert_eq!(err.user_message(), INTERNAL_ERROR_MESSAGE);
+        assert_eq!(err.http_status(), 500);
+        assert!(matches!(
+            err.source().unwrap().downcast_ref(),
+            Some(CommonError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn crypto_message_is_kept_verbatim() {
+        assert_eq!(CommonError::crypto("x").to_string(), "crypto error: x");
+        assert_eq!(
+            CommonError::from(CryptoError::OperationFailed("x".into())).to_string(),
+            "crypto error: Crypto operation failed: x"
+        );
+    }
+
+    #[test]
+    fn token_errors_gain_fields_without_losing_code() {
+        let err = CommonError::TokenExpired.with_field("authorization", "expired");
+        assert_eq!(err.code().to_string(), "40190010101");
+        assert_eq!(err.user_message(), "token expired");
+        assert!(err.source().is_some());
     }
 }
@@ -14,12 +14,19 @@ pub mod utils;
 
 // Re-export commonly used types for convenience
 pub use config::Config;
-pub use crypto::CryptoError;
+pub use crypto::{CryptoError, Secret};
 pub use dto::ApiResponse;
-pub use error::CommonError;
+pub use error::{CommonError, ResultExt};
 pub use model::Pagination;
 pub use utils::ErrorCode;
-pub use utils::{StructuredCode, is_valid_code, make_code, parse_code};
+pub use utils::{FieldError, Validate, ValidationErrors};
+pub use utils::{StructuredCode, is_valid_code, make_code, parse_code, register_category};
+
+/// `#[derive(Validate)]`, see [`utils::validation_errors`]
+pub use cland_rust_share_derive::Validate;
+
+// Lets derive output written against `::cland_rust_share` compile inside this crate
+extern crate self as cland_rust_share;
 
 /// Library version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
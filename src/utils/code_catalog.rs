@@ -0,0 +1,198 @@
+//! Exportable catalog of every registered error code.
+//!
+//! Built from an [`ErrorCodeRegistry`] so the list handed to front-end and QA
+//! teams is generated instead of maintained by hand:
+//!
+//! ```
+//! use cland_rust_share::utils::code_catalog::Catalog;
+//!
+//! let catalog = Catalog::from_registry(&cland_rust_share::error::builtin_codes().unwrap());
+//! let json = catalog.to_json();
+//! assert_eq!(json[0]["code"], 40090010001i64);
+//! assert!(catalog.to_markdown().contains("| 40090010001 |"));
+//! ```
+//!
+//! The `error_catalog` example prints the crate's own catalog in any format.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::error_codes::ErrorCodeRegistry;
+
+/// Name of the OpenAPI extension written by [`Catalog::to_openapi_extension`]
+pub const OPENAPI_EXTENSION: &str = "x-error-codes";
+
+/// One row of the catalog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CatalogEntry {
+    /// Full 11-digit code
+    pub code: i64,
+    /// Category, e.g. `400`
+    pub category: i32,
+    /// System id
+    pub system: i32,
+    /// Detail
+    pub detail: i32,
+    /// HTTP status sent with the code
+    pub http_status: u16,
+    /// `Set::Variant`
+    pub name: String,
+    /// Default message
+    pub message: String,
+    /// Translated messages by locale
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub locales: BTreeMap<String, String>,
+}
+
+/// Every registered code, ordered by system, then detail
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    /// Catalog of every code in `registry`
+    pub fn from_registry(registry: &ErrorCodeRegistry) -> Self {
+        let entries = registry
+            .entries()
+            .map(|e| CatalogEntry {
+                code: e.code.value(),
+                category: e.code.category,
+                system: e.code.system,
+                detail: e.code.detail,
+                http_status: e.code.http_status(),
+                name: format!("{}::{}", e.set, e.name),
+                message: e.message.to_string(),
+                locales: e
+                    .locales
+                    .iter()
+                    .map(|(l, m)| (l.to_string(), m.to_string()))
+                    .collect(),
+            })
+            .collect();
+        Catalog { entries }
+    }
+
+    /// Catalog rows
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    /// Every locale with at least one translation, sorted
+    pub fn locales(&self) -> Vec<&str> {
+        let all: BTreeSet<&str> = self
+            .entries
+            .iter()
+            .flat_map(|e| e.locales.keys().map(String::as_str))
+            .collect();
+        all.into_iter().collect()
+    }
+
+    /// JSON array of entries
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(&self.entries).unwrap_or(Value::Null)
+    }
+
+    /// Markdown table in the style of the HTTP message standard, one column per locale
+    pub fn to_markdown(&self) -> String {
+        let locales = self.locales();
+        let mut out = String::from("| 状态码 | 异常类别 | 异常系统 | 异常自定义 | 名称 | 描述 |");
+        for locale in &locales {
+            out.push_str(&format!(" {} |", locale));
+        }
+        out.push_str("\n| --- | --- | --- | --- | --- | --- |");
+        out.push_str(&" --- |".repeat(locales.len()));
+        for e in &self.entries {
+            out.push_str(&format!(
+                "\n| {} | {} | {:04} | {:04} | `{}` | {} |",
+                e.code,
+                e.category,
+                e.system,
+                e.detail,
+                e.name,
+                escape_cell(&e.message)
+            ));
+            for locale in &locales {
+                let text = e.locales.get(*locale).map(String::as_str).unwrap_or("");
+                out.push_str(&format!(" {} |", escape_cell(text)));
+            }
+        }
+        out.push('\n');
+        out
+    }
+
+    /// `{"x-error-codes": [...]}`, to merge into the root of an OpenAPI document
+    pub fn to_openapi_extension(&self) -> Value {
+        serde_json::json!({ OPENAPI_EXTENSION: self.to_json() })
+    }
+
+    /// Add the `x-error-codes` extension to a utoipa OpenAPI document
+    #[cfg(feature = "utoipa_support")]
+    pub fn extend_openapi(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .extensions
+            .get_or_insert_with(Default::default)
+            .insert(OPENAPI_EXTENSION.to_string(), self.to_json());
+    }
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::define_error_codes! {
+        enum Sample: 4242 {
+            Missing(404, 2) => "missing | gone" { "zh-CN": "不存在" },
+            Broken(500, 1) => "broken",
+        }
+    }
+
+    fn catalog() -> Catalog {
+        Catalog::from_registry(&ErrorCodeRegistry::new().register::<Sample>().unwrap())
+    }
+
+    #[test]
+    fn json_rows_are_ordered() {
+        let json = catalog().to_json();
+        assert_eq!(json[0]["code"], 50042420001i64);
+        assert_eq!(json[1]["http_status"], 404);
+        assert_eq!(json[1]["locales"]["zh-CN"], "不存在");
+        assert!(json[0].get("locales").is_none());
+    }
+
+    #[test]
+    fn markdown_table() {
+        let md = catalog().to_markdown();
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(
+            lines[0],
+            "| 状态码 | 异常类别 | 异常系统 | 异常自定义 | 名称 | 描述 | zh-CN |"
+        );
+        assert_eq!(
+            lines[3],
+            "| 40442420002 | 404 | 4242 | 0002 | `Sample::Missing` | missing \\| gone | 不存在 |"
+        );
+    }
+
+    #[test]
+    fn openapi_extension() {
+        let ext = catalog().to_openapi_extension();
+        assert_eq!(ext[OPENAPI_EXTENSION].as_array().unwrap().len(), 2);
+    }
+
+    #[cfg(feature = "utoipa_support")]
+    #[test]
+    fn utoipa_document() {
+        let mut doc = utoipa::openapi::OpenApiBuilder::new().build();
+        catalog().extend_openapi(&mut doc);
+        let json = serde_json::to_value(&doc).unwrap();
+        assert_eq!(json[OPENAPI_EXTENSION][1]["name"], "Sample::Missing");
+    }
+}
@@ -0,0 +1,392 @@
+//! Declarative error code sets and a registry that checks them for clashes.
+//!
+//! Each service declares its codes once with [`define_error_codes!`](crate::define_error_codes) instead
+//! of calling `make_code(400, 1001, 1)` at every use site:
+//!
+//! ```
+//! use cland_rust_share::{StructuredCode, define_error_codes};
+//!
+//! define_error_codes! {
+//!     /// Order service errors
+//!     pub enum OrderCode: 1001 {
+//!         /// The order id does not exist
+//!         NotFound(400, 1) => "order not found" { "zh-CN": "订单不存在" },
+//!         AlreadyPaid(400, 2) => "order already paid",
+//!         Internal(500, 3) => "order service failure",
+//!     }
+//! }
+//!
+//! let code: StructuredCode = OrderCode::AlreadyPaid.into();
+//! assert_eq!(code.to_string(), "40010010002");
+//! assert_eq!(OrderCode::AlreadyPaid.to_string(), "order already paid");
+//! assert_eq!(OrderCode::NotFound.message_for("zh-CN"), "订单不存在");
+//! ```
+//!
+//! Translations are optional; [`message_for`](CodeEntry::message_for) falls
+//! back to the default message. The registry feeds the exported
+//! [`Catalog`](super::code_catalog::Catalog).
+//!
+//! Duplicate details inside one set fail to compile. Clashes between sets,
+//! e.g. two teams claiming the same system id, are caught by registering every
+//! set in an [`ErrorCodeRegistry`] from a test:
+//!
+//! ```ignore
+//! #[test]
+//! fn error_codes_are_unique() {
+//!     ErrorCodeRegistry::new()
+//!         .register::<OrderCode>()
+//!         .and_then(|r| r.register::<PaymentCode>())
+//!         .unwrap();
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::http_code::{CodeError, StructuredCode, make_code};
+
+/// One declared code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeEntry {
+    /// Name of the enum that declares the code
+    pub set: &'static str,
+    /// Variant name
+    pub name: &'static str,
+    /// Structured code
+    pub code: StructuredCode,
+    /// Default message
+    pub message: &'static str,
+    /// Translated messages as `(locale, message)` pairs, e.g. `("zh-CN", "订单不存在")`
+    pub locales: &'static [(&'static str, &'static str)],
+}
+
+impl CodeEntry {
+    /// Message for `locale`, or the default message when there is no translation
+    pub fn message_for(&self, locale: &str) -> &'static str {
+        lookup_locale(self.locales, locale).unwrap_or(self.message)
+    }
+}
+
+#[doc(hidden)]
+pub fn lookup_locale(
+    locales: &'static [(&'static str, &'static str)],
+    locale: &str,
+) -> Option<&'static str> {
+    locales
+        .iter()
+        .find(|(l, _)| l.eq_ignore_ascii_case(locale))
+        .map(|(_, m)| *m)
+}
+
+impl fmt::Display for CodeEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{} ({})", self.set, self.name, self.code)
+    }
+}
+
+/// A set of codes generated by [`define_error_codes!`](crate::define_error_codes)
+pub trait ErrorCodeSet {
+    /// Enum name
+    const NAME: &'static str;
+
+    /// System id shared by every code in the set
+    const SYSTEM: i32;
+
+    /// Every code in declaration order
+    fn entries() -> Vec<CodeEntry>;
+}
+
+/// Registration failure
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    /// Two codes share the same system and detail
+    #[error("{second} reuses system {system} detail {detail} of {first}")]
+    Duplicate {
+        system: i32,
+        detail: i32,
+        first: String,
+        second: String,
+    },
+
+    /// Code is not a valid structured code
+    #[error("{entry} is invalid: {source}")]
+    Invalid {
+        entry: String,
+        #[source]
+        source: CodeError,
+    },
+}
+
+/// Every code known to an application, keyed by `(system, detail)`
+#[derive(Debug, Clone, Default)]
+pub struct ErrorCodeRegistry {
+    entries: BTreeMap<(i32, i32), CodeEntry>,
+}
+
+impl ErrorCodeRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add every code of `S`, failing on an invalid code or a `(system, detail)` clash
+    pub fn register<S: ErrorCodeSet>(self) -> Result<Self, RegistryError> {
+        self.register_entries(S::entries())
+    }
+
+    /// Add codes that were not declared with [`define_error_codes!`](crate::define_error_codes)
+    pub fn register_entries(
+        mut self,
+        entries: impl IntoIterator<Item = CodeEntry>,
+    ) -> Result<Self, RegistryError> {
+        for entry in entries {
+            let code = &entry.code;
+            if let Err(source) = make_code(code.category, code.system, code.detail) {
+                return Err(RegistryError::Invalid {
+                    entry: entry.to_string(),
+                    source,
+                });
+            }
+            let key = (code.system, code.detail);
+            if let Some(first) = self.entries.get(&key) {
+                return Err(RegistryError::Duplicate {
+                    system: key.0,
+                    detail: key.1,
+                    first: first.to_string(),
+                    second: entry.to_string(),
+                });
+            }
+            self.entries.insert(key, entry);
+        }
+        Ok(self)
+    }
+
+    /// Entry for `system` and `detail`
+    pub fn get(&self, system: i32, detail: i32) -> Option<&CodeEntry> {
+        self.entries.get(&(system, detail))
+    }
+
+    /// All entries ordered by system, then detail
+    pub fn entries(&self) -> impl Iterator<Item = &CodeEntry> {
+        self.entries.values()
+    }
+
+    /// Number of registered codes
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no code is registered
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Declare a set of structured codes for one system id
+///
+/// Generates a `Copy` enum with `code()`, `value()`, `category()`, `detail()`,
+/// `message()`, `message_for(locale)`, `locales()` and `name()`, plus `Into<StructuredCode>`, `Display` (the
+/// default message) and [`ErrorCodeSet`]. See the [module docs](crate::utils::error_codes).
+#[macro_export]
+macro_rules! define_error_codes {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident : $system:literal {
+            $(
+                $(#[$vmeta:meta])*
+                $variant:ident($category:literal, $detail:literal) => $message:literal
+                $({ $($locale:literal : $translation:literal),* $(,)? })?
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $(
+                $(#[$vmeta])*
+                $variant,
+            )+
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// System id shared by every code in the set
+            pub const SYSTEM: i32 = $system;
+
+            /// Every code in declaration order
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            /// Category part of the code, e.g. `400`
+            pub const fn category(self) -> i32 {
+                match self {
+                    $($name::$variant => $category,)+
+                }
+            }
+
+            /// Detail part of the code
+            pub const fn detail(self) -> i32 {
+                match self {
+                    $($name::$variant => $detail,)+
+                }
+            }
+
+            /// Default message
+            pub const fn message(self) -> &'static str {
+                match self {
+                    $($name::$variant => $message,)+
+                }
+            }
+
+            /// Translated messages as `(locale, message)` pairs
+            pub const fn locales(self) -> &'static [(&'static str, &'static str)] {
+                match self {
+                    $($name::$variant => &[$($(($locale, $translation)),*)?],)+
+                }
+            }
+
+            /// Message for `locale`, or the default message when there is no translation
+            pub fn message_for(self, locale: &str) -> &'static str {
+                $crate::utils::error_codes::lookup_locale(self.locales(), locale)
+                    .unwrap_or(self.message())
+            }
+
+            /// Variant name
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($variant),)+
+                }
+            }
+
+            /// Structured code
+            pub const fn code(self) -> $crate::utils::StructuredCode {
+                $crate::utils::StructuredCode {
+                    category: self.category(),
+                    system: Self::SYSTEM,
+                    detail: self.detail(),
+                }
+            }
+
+            /// Integer form, same as [`StructuredCode::value`]($crate::utils::StructuredCode::value)
+            pub const fn value(self) -> i64 {
+                self.code().value()
+            }
+        }
+
+        const _: () = {
+            let details = [$($detail),+];
+            assert!(
+                $system >= 0 && $system <= 9999,
+                concat!("system id of ", stringify!($name), " must be 0..=9999")
+            );
+            let mut i = 0;
+            while i < details.len() {
+                assert!(
+                    details[i] >= 0 && details[i] <= 9999,
+                    concat!("detail in ", stringify!($name), " must be 0..=9999")
+                );
+                let mut j = i + 1;
+                while j < details.len() {
+                    assert!(
+                        details[i] != details[j],
+                        concat!("duplicate detail in ", stringify!($name))
+                    );
+                    j += 1;
+                }
+                i += 1;
+            }
+        };
+
+        impl ::std::convert::From<$name> for $crate::utils::StructuredCode {
+            fn from(code: $name) -> Self {
+                code.code()
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.message())
+            }
+        }
+
+        impl $crate::utils::error_codes::ErrorCodeSet for $name {
+            const NAME: &'static str = stringify!($name);
+            const SYSTEM: i32 = $system;
+
+            fn entries() -> ::std::vec::Vec<$crate::utils::error_codes::CodeEntry> {
+                Self::ALL
+                    .iter()
+                    .map(|c| $crate::utils::error_codes::CodeEntry {
+                        set: stringify!($name),
+                        name: c.name(),
+                        code: c.code(),
+                        message: c.message(),
+                        locales: c.locales(),
+                    })
+                    .collect()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::define_error_codes! {
+        enum First: 1234 {
+            A(400, 1) => "a" { "zh-CN": "甲" },
+            B(500, 2) => "b",
+        }
+    }
+
+    crate::define_error_codes! {
+        enum Clash: 1234 {
+            C(400, 2) => "c",
+        }
+    }
+
+    crate::define_error_codes! {
+        enum Short: 0 {
+            Done(200, 0) => "done",
+        }
+    }
+
+    crate::define_error_codes! {
+        enum BadCategory: 4321 {
+            D(201, 1) => "d",
+        }
+    }
+
+    #[test]
+    fn generated_enum() {
+        assert_eq!(First::B.value(), 50012340002);
+        assert_eq!(StructuredCode::from(First::A).to_string(), "40012340001");
+        assert_eq!(First::A.to_string(), "a");
+        assert_eq!(First::ALL, &[First::A, First::B]);
+        assert_eq!(First::entries()[1].name, "B");
+        assert_eq!(First::A.message_for("zh-cn"), "甲");
+        assert_eq!(First::B.message_for("zh-CN"), "b");
+        assert_eq!(Short::Done.value(), 200);
+        assert_eq!(
+            Short::Done.value(),
+            StructuredCode::from(Short::Done).value()
+        );
+    }
+
+    #[test]
+    fn registry_rejects_clashes() {
+        let registry = ErrorCodeRegistry::new().register::<First>().unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get(1234, 1).unwrap().message, "a");
+
+        let err = registry.clone().register::<Clash>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Clash::C (40012340002) reuses system 1234 detail 2 of First::B (50012340002)"
+        );
+        assert!(matches!(
+            registry.register::<BadCategory>(),
+            Err(RegistryError::Invalid { .. })
+        ));
+    }
+}
@@ -1,7 +1,16 @@
+use std::collections::BTreeSet;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 
 /// Represents parsed parts of the structured code.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Ordered by category, then system, then detail. Serializes as an integer
+/// (see [`StructuredCode::value`]) and deserializes from an integer or a
+/// string. Use [`StringCode`] or [`code_as_string`] where a string is needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct StructuredCode {
     /// category: 3 digits (e.g., 200, 400, 500)
     pub category: i32,
@@ -13,7 +22,7 @@ pub struct StructuredCode {
 
 #[derive(Debug, thiserror::Error)]
 pub enum CodeError {
-    #[error("invalid length: expected 3,4 or 11 digits")]
+    #[error("invalid length: expected 3 or 11 digits")]
     InvalidLength,
     #[error("invalid numeric value")]
     InvalidNumber,
@@ -21,9 +30,13 @@ pub enum CodeError {
     InvalidCategory(i32),
 }
 
+/// Same digits as [`StructuredCode::value`]: `"400"` for a bare category,
+/// otherwise the full 11-digit code.
 impl fmt::Display for StructuredCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // produce full 11-digit code as i64-safe string
+        if self.is_category_only() {
+            return write!(f, "{:03}", self.category);
+        }
         write!(
             f,
             "{category:03}{system:04}{detail:04}",
@@ -34,26 +47,287 @@ impl fmt::Display for StructuredCode {
     }
 }
 
-/// Generate an 11-digit code from parts.
+impl StructuredCode {
+    /// Integer form: the bare category (e.g. `200`) when system and detail are
+    /// zero, otherwise the full 11-digit code.
+    ///
+    /// This is the canonical form used by `Display`, serde and [`make_code`];
+    /// the 11-digit spelling of a bare category (`40000000000`) is accepted
+    /// when parsing and normalised to `400`.
+    pub const fn value(&self) -> i64 {
+        if self.is_category_only() {
+            return self.category as i64;
+        }
+        (self.category as i64) * 100_000_000 + (self.system as i64) * 10_000 + (self.detail as i64)
+    }
+
+    const fn is_category_only(&self) -> bool {
+        self.system == 0 && self.detail == 0
+    }
+
+    /// HTTP status to send for this code, e.g. `400` for `40010010001`.
+    ///
+    /// The category is used as is when it is a valid HTTP status (100..=599);
+    /// other custom categories map to `500`.
+    pub fn http_status(&self) -> u16 {
+        match self.category {
+            100..=599 => self.category as u16,
+            _ => 500,
+        }
+    }
+
+    /// [`http_status`](Self::http_status) as an `http::StatusCode`.
+    #[cfg(feature = "http")]
+    pub fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::from_u16(self.http_status())
+            .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Parses `"404"` (short) or `"40010010001"` (full); anything else is
+/// [`CodeError::InvalidLength`] or [`CodeError::InvalidNumber`].
+impl FromStr for StructuredCode {
+    type Err = CodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(CodeError::InvalidNumber);
+        }
+        if s.len() != 3 && s.len() != 11 {
+            return Err(CodeError::InvalidLength);
+        }
+        let code: i64 = s.parse().map_err(|_| CodeError::InvalidNumber)?;
+        if s.len() == 3 && !is_known_category(code as i32) {
+            return Err(CodeError::InvalidCategory(code as i32));
+        }
+        parse_code(code)
+    }
+}
+
+impl TryFrom<i64> for StructuredCode {
+    type Error = CodeError;
+
+    fn try_from(code: i64) -> Result<Self, Self::Error> {
+        parse_code(code)
+    }
+}
+
+impl From<StructuredCode> for i64 {
+    fn from(code: StructuredCode) -> Self {
+        code.value()
+    }
+}
+
+impl Serialize for StructuredCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.value())
+    }
+}
+
+impl<'de> Deserialize<'de> for StructuredCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(CodeVisitor)
+    }
+}
+
+struct CodeVisitor;
+
+impl de::Visitor<'_> for CodeVisitor {
+    type Value = StructuredCode;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a structured code as integer or string")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<StructuredCode, E> {
+        parse_code(v).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<StructuredCode, E> {
+        let v = i64::try_from(v).map_err(|_| E::custom(CodeError::InvalidNumber))?;
+        self.visit_i64(v)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<StructuredCode, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+#[cfg(feature = "utoipa_support")]
+impl utoipa::PartialSchema for StructuredCode {
+    fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+        use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, SchemaFormat, Type};
+
+        ObjectBuilder::new()
+            .schema_type(Type::Integer)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64)))
+            .examples([serde_json::json!(40010010001i64)])
+            .description(Some(
+                "Structured code: 3-digit category or 11-digit category|system|detail",
+            ))
+            .into()
+    }
+}
+
+#[cfg(feature = "utoipa_support")]
+impl utoipa::ToSchema for StructuredCode {}
+
+/// Serde helper writing a [`StructuredCode`] as a string (`"40010010001"`),
+/// for callers that cannot take the integer form:
+///
+/// ```
+/// use cland_rust_share::StructuredCode;
+///
+/// #[derive(serde::Serialize)]
+/// struct LegacyBody {
+///     #[serde(with = "cland_rust_share::utils::http_code::code_as_string")]
+///     code: StructuredCode,
+/// }
+///
+/// let body = LegacyBody { code: StructuredCode::from(cland_rust_share::ErrorCode::Ok) };
+/// assert_eq!(serde_json::to_string(&body).unwrap(), r#"{"code":"200"}"#);
+/// ```
+///
+/// Deserializing accepts both forms, like [`StructuredCode`] itself.
+pub mod code_as_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::StructuredCode;
+
+    pub fn serialize<S: Serializer>(
+        code: &StructuredCode,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&code.value())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<StructuredCode, D::Error> {
+        StructuredCode::deserialize(deserializer)
+    }
+}
+
+/// [`StructuredCode`] that serializes as a string
 ///
-/// - `category` should be 200, 400, 500 (or compatible)
+/// Used as `ApiResponse<T, StringCode>` (see `ApiResponse::with_string_code`)
+/// for clients that only accept string codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct StringCode(#[serde(with = "code_as_string")] pub StructuredCode);
+
+impl From<StructuredCode> for StringCode {
+    fn from(code: StructuredCode) -> Self {
+        StringCode(code)
+    }
+}
+
+impl From<StringCode> for StructuredCode {
+    fn from(code: StringCode) -> Self {
+        code.0
+    }
+}
+
+impl From<ErrorCode> for StringCode {
+    fn from(code: ErrorCode) -> Self {
+        StringCode(code.into())
+    }
+}
+
+impl fmt::Display for StringCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(feature = "utoipa_support")]
+impl utoipa::PartialSchema for StringCode {
+    fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+        use utoipa::openapi::schema::{ObjectBuilder, Type};
+
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .pattern(Some("^[0-9]{3}([0-9]{8})?$"))
+            .examples([serde_json::json!("40010010001")])
+            .description(Some(
+                "Structured code: 3-digit category or 11-digit category|system|detail",
+            ))
+            .into()
+    }
+}
+
+#[cfg(feature = "utoipa_support")]
+impl utoipa::ToSchema for StringCode {}
+
+impl From<ErrorCode> for StructuredCode {
+    fn from(code: ErrorCode) -> Self {
+        code.as_structured()
+    }
+}
+
+/// Custom categories added at startup with [`register_category`].
+static CUSTOM_CATEGORIES: RwLock<BTreeSet<i32>> = RwLock::new(BTreeSet::new());
+
+/// Whether `category` is an [`ErrorCode`] or was added with [`register_category`].
+pub fn is_known_category(category: i32) -> bool {
+    ErrorCode::from_category(category).is_some()
+        || CUSTOM_CATEGORIES
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(&category)
+}
+
+/// Accept an extra 3-digit category (100..=999) in `make_code` and `parse_code`.
+///
+/// Meant to be called once at startup; registering a category twice is a no-op.
+/// Registration is process-wide and permanent: there is no way to remove a
+/// category again.
+pub fn register_category(category: i32) -> Result<(), CodeError> {
+    if !(100..=999).contains(&category) {
+        return Err(CodeError::InvalidCategory(category));
+    }
+    CUSTOM_CATEGORIES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(category);
+    Ok(())
+}
+
+/// All accepted categories, built-in and custom, in ascending order.
+pub fn known_categories() -> Vec<i32> {
+    let mut all: BTreeSet<i32> = ErrorCode::ALL.iter().map(|c| *c as i32).collect();
+    all.extend(
+        CUSTOM_CATEGORIES
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter(),
+    );
+    all.into_iter().collect()
+}
+
+/// Generate a code from parts, in the form of [`StructuredCode::value`]:
+/// the bare category when `system` and `detail` are both 0, otherwise 11 digits.
+///
+/// - `category` should be an [`ErrorCode`] or a category added with [`register_category`]
 /// - `system` and `detail` are 0..=9999
 pub fn make_code(category: i32, system: i32, detail: i32) -> Result<i64, CodeError> {
-    if category != 200 && category != 400 && category != 500 {
+    if !is_known_category(category) {
         return Err(CodeError::InvalidCategory(category));
     }
     if !(0..=9999).contains(&system) || !(0..=9999).contains(&detail) {
         return Err(CodeError::InvalidNumber);
     }
-    // safe to compute as i64
-    // category occupies the highest 3 digits of an 11-digit number -> multiplier 10^8
-    let code = (category as i64) * 100_000_000 + (system as i64) * 10_000 + (detail as i64);
-    Ok(code)
+    let code = StructuredCode {
+        category,
+        system,
+        detail,
+    };
+    Ok(code.value())
 }
 
-/// Parse an integer code into StructuredCode. Accepts either 200/400/500 (short) or full 11-digit codes.
+/// Parse an integer code into StructuredCode. Accepts either a known category (short) or full 11-digit codes.
 pub fn parse_code(code: i64) -> Result<StructuredCode, CodeError> {
-    if code == 200 || code == 400 || code == 500 {
+    if (100..1000).contains(&code) && is_known_category(code as i32) {
         return Ok(StructuredCode {
             category: code as i32,
             system: 0,
@@ -67,7 +341,7 @@ pub fn parse_code(code: i64) -> Result<StructuredCode, CodeError> {
     let category = (code / 100_000_000) as i32;
     let system = ((code / 10_000) % 10_000) as i32;
     let detail = (code % 10_000) as i32;
-    if !(category == 200 || category == 400 || category == 500) {
+    if !is_known_category(category) {
         return Err(CodeError::InvalidCategory(category));
     }
     Ok(StructuredCode {
@@ -83,14 +357,44 @@ pub fn is_valid_code(code: i64) -> bool {
 }
 
 /// Common error categories as enum. Values are short-form categories.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ErrorCode {
     Ok = 200,
     BadRequest = 400,
+    Unauthorized = 401,
+    Forbidden = 403,
+    NotFound = 404,
+    Conflict = 409,
+    UnprocessableEntity = 422,
+    TooManyRequests = 429,
     Internal = 500,
+    BadGateway = 502,
+    ServiceUnavailable = 503,
+    GatewayTimeout = 504,
 }
 
 impl ErrorCode {
+    /// every built-in category, ascending
+    pub const ALL: [ErrorCode; 12] = [
+        ErrorCode::Ok,
+        ErrorCode::BadRequest,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::NotFound,
+        ErrorCode::Conflict,
+        ErrorCode::UnprocessableEntity,
+        ErrorCode::TooManyRequests,
+        ErrorCode::Internal,
+        ErrorCode::BadGateway,
+        ErrorCode::ServiceUnavailable,
+        ErrorCode::GatewayTimeout,
+    ];
+
+    /// built-in category for `category`, if any
+    pub fn from_category(category: i32) -> Option<ErrorCode> {
+        ErrorCode::ALL.into_iter().find(|c| *c as i32 == category)
+    }
+
     /// numeric value
     pub fn value(self) -> i64 {
         self as i64
@@ -134,6 +438,75 @@ mod tests {
         assert!(r.is_err());
         let p = parse_code(201i64);
         assert!(p.is_err());
+        // never registered by any test, unlike the category in extended_and_custom_categories
+        assert!(make_code(598, 1, 1).is_err());
+    }
+
+    #[test]
+    fn extended_and_custom_categories() {
+        assert_eq!(parse_code(404).unwrap().category, 404);
+        assert_eq!(make_code(429, 1001, 7).unwrap(), 42910010007);
+        assert_eq!(
+            ErrorCode::from_category(503),
+            Some(ErrorCode::ServiceUnavailable)
+        );
+
+        // registration is permanent, so use a category no other test relies on
+        register_category(987).unwrap();
+        assert_eq!(parse_code(98700010001).unwrap().category, 987);
+        assert_eq!(parse_code(98700010001).unwrap().http_status(), 500);
+        assert!(known_categories().contains(&987));
+        assert!(register_category(1000).is_err());
+    }
+
+    #[test]
+    fn integer_value() {
+        assert_eq!(ErrorCode::Ok.as_structured().value(), 200);
+        assert_eq!(parse_code(40010010001).unwrap().value(), 40010010001);
+    }
+
+    #[test]
+    fn canonical_form_round_trips() {
+        for code in [
+            ErrorCode::BadRequest.as_structured(),
+            parse_code(40000000000).unwrap(),
+            parse_code(40010010001).unwrap(),
+        ] {
+            let json = serde_json::to_string(&code).unwrap();
+            let back: StructuredCode = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, code);
+            assert_eq!(back.to_string(), json);
+            assert_eq!(back.to_string().parse::<i64>().unwrap(), back.value());
+            assert_eq!(
+                make_code(code.category, code.system, code.detail).unwrap(),
+                code.value()
+            );
+        }
+        assert_eq!(parse_code(40000000000).unwrap().to_string(), "400");
+    }
+
+    #[test]
+    fn from_str_and_conversions() {
+        assert_eq!("404".parse::<StructuredCode>().unwrap().category, 404);
+        let full: StructuredCode = "40010010001".parse().unwrap();
+        assert_eq!(full, StructuredCode::try_from(40010010001).unwrap());
+        assert_eq!(i64::from(full), 40010010001);
+        assert!(matches!(
+            "4001001".parse::<StructuredCode>(),
+            Err(CodeError::InvalidLength)
+        ));
+        assert!(matches!(
+            "40x".parse::<StructuredCode>(),
+            Err(CodeError::InvalidNumber)
+        ));
+        assert!(matches!(
+            "201".parse::<StructuredCode>(),
+            Err(CodeError::InvalidCategory(201))
+        ));
+        assert_eq!(full.http_status(), 400);
+        #[cfg(feature = "http")]
+        assert_eq!(full.status_code(), http::StatusCode::BAD_REQUEST);
+        assert!(ErrorCode::Ok.as_structured() < full);
     }
 
     #[test]
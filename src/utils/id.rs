@@ -0,0 +1,465 @@
+//! Unique id generation: UUID v4/v7, ULID and Snowflake.
+//!
+//! - UUID v4: 122 random bits, for opaque ids and trace ids.
+//! - UUID v7 and ULID: millisecond timestamp followed by random bits, so ids
+//!   sort roughly by creation time and index well as primary keys.
+//! - Snowflake: 64-bit integers from a [`SnowflakeGenerator`], laid out as
+//!   `0 | timestamp:41 | datacenter:5 | worker:5 | sequence:12`, strictly
+//!   increasing per generator and unique across up to 1024 workers.
+//!
+//! The `*_with_rng` variants take a [`RandomSource`] for reproducible tests.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+pub use ulid::Ulid;
+pub use uuid::Uuid;
+
+use super::current_timestamp_millis;
+use crate::config::Config;
+use crate::crypto::{CryptoError, OsRandom, RandomSource};
+
+/// Default Snowflake epoch: 2020-01-01T00:00:00Z in Unix milliseconds
+pub const DEFAULT_SNOWFLAKE_EPOCH_MS: u64 = 1_577_836_800_000;
+
+/// Largest datacenter id (5 bits)
+pub const MAX_DATACENTER_ID: u16 = 31;
+
+/// Largest worker id (5 bits)
+pub const MAX_WORKER_ID: u16 = 31;
+
+const SEQUENCE_BITS: u32 = 12;
+const WORKER_BITS: u32 = 5;
+const DATACENTER_BITS: u32 = 5;
+const TIMESTAMP_BITS: u32 = 41;
+const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+const MAX_TIMESTAMP: u64 = (1 << TIMESTAMP_BITS) - 1;
+
+/// Id generation failure
+#[derive(Debug, thiserror::Error)]
+pub enum IdError {
+    /// System clock went backwards further than the rollback policy allows
+    #[error("clock moved backwards by {behind_ms}ms")]
+    ClockMovedBackwards { behind_ms: u64 },
+
+    /// Snowflake settings out of range or unparsable
+    #[error("invalid id generator config: {0}")]
+    InvalidConfig(String),
+
+    /// Timestamp does not fit the 41-bit Snowflake field for this epoch
+    #[error("timestamp out of range for the configured epoch")]
+    TimestampOutOfRange,
+
+    /// Random source failed
+    #[error(transparent)]
+    Random(#[from] CryptoError),
+}
+
+impl From<IdError> for crate::CommonError {
+    fn from(err: IdError) -> Self {
+        crate::CommonError::internal(err)
+    }
+}
+
+/// Random UUID (version 4)
+pub fn uuid_v4() -> Result<Uuid, IdError> {
+    uuid_v4_with_rng(&mut OsRandom)
+}
+
+/// Random UUID (version 4) drawn from `rng`
+pub fn uuid_v4_with_rng(rng: &mut impl RandomSource) -> Result<Uuid, IdError> {
+    let mut bytes = [0u8; 16];
+    rng.fill_random(&mut bytes)?;
+    Ok(uuid::Builder::from_random_bytes(bytes).into_uuid())
+}
+
+/// Time-ordered UUID (version 7) for the current time
+pub fn uuid_v7() -> Result<Uuid, IdError> {
+    uuid_v7_with_rng(&mut OsRandom)
+}
+
+/// Time-ordered UUID (version 7) with random bits drawn from `rng`
+pub fn uuid_v7_with_rng(rng: &mut impl RandomSource) -> Result<Uuid, IdError> {
+    let mut bytes = [0u8; 10];
+    rng.fill_random(&mut bytes)?;
+    Ok(uuid::Builder::from_unix_timestamp_millis(now_millis(), &bytes).into_uuid())
+}
+
+/// Unix milliseconds embedded in a version 7 UUID, or `None` for other versions
+pub fn uuid_v7_timestamp(id: &Uuid) -> Option<u64> {
+    if id.get_version_num() != 7 {
+        return None;
+    }
+    let b = id.as_bytes();
+    Some(
+        b[..6]
+            .iter()
+            .fold(0u64, |acc, byte| (acc << 8) | *byte as u64),
+    )
+}
+
+/// ULID for the current time
+pub fn ulid() -> Result<Ulid, IdError> {
+    ulid_with_rng(&mut OsRandom)
+}
+
+/// ULID with random bits drawn from `rng`
+pub fn ulid_with_rng(rng: &mut impl RandomSource) -> Result<Ulid, IdError> {
+    let mut bytes = [0u8; 16];
+    rng.fill_random(&mut bytes[6..])?;
+    Ok(Ulid::from_parts(now_millis(), u128::from_be_bytes(bytes)))
+}
+
+/// Unix milliseconds embedded in a ULID
+pub fn ulid_timestamp(id: &Ulid) -> u64 {
+    id.timestamp_ms()
+}
+
+/// What a [`SnowflakeGenerator`] does when the clock goes backwards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockRollback {
+    /// Fail immediately with [`IdError::ClockMovedBackwards`]
+    Error,
+    /// Sleep until the clock catches up, failing if that takes longer than the limit
+    Wait(Duration),
+}
+
+/// Snowflake generator settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnowflakeConfig {
+    /// Epoch in Unix milliseconds; ids are valid for about 69 years after it
+    pub epoch_ms: u64,
+    /// Datacenter id, `0..=31`
+    pub datacenter_id: u16,
+    /// Worker id, `0..=31`, unique per process within a datacenter
+    pub worker_id: u16,
+    /// Behaviour on clock rollback
+    pub clock_rollback: ClockRollback,
+}
+
+impl Default for SnowflakeConfig {
+    fn default() -> Self {
+        SnowflakeConfig {
+            epoch_ms: DEFAULT_SNOWFLAKE_EPOCH_MS,
+            datacenter_id: 0,
+            worker_id: 0,
+            clock_rollback: ClockRollback::Wait(Duration::from_millis(50)),
+        }
+    }
+}
+
+impl SnowflakeConfig {
+    /// Read settings from `Config::custom`, falling back to environment variables
+    ///
+    /// | `custom` key               | env var                   |
+    /// |----------------------------|---------------------------|
+    /// | `snowflake.worker_id`      | `SNOWFLAKE_WORKER_ID`     |
+    /// | `snowflake.datacenter_id`  | `SNOWFLAKE_DATACENTER_ID` |
+    /// | `snowflake.epoch_ms`       | `SNOWFLAKE_EPOCH_MS`      |
+    ///
+    /// Unset values keep their defaults.
+    pub fn from_config(config: &Config) -> Result<Self, IdError> {
+        let mut settings = SnowflakeConfig::default();
+        if let Some(v) = lookup(config, "snowflake.worker_id", "SNOWFLAKE_WORKER_ID") {
+            settings.worker_id = parse_setting("worker_id", &v)?;
+        }
+        if let Some(v) = lookup(config, "snowflake.datacenter_id", "SNOWFLAKE_DATACENTER_ID") {
+            settings.datacenter_id = parse_setting("datacenter_id", &v)?;
+        }
+        if let Some(v) = lookup(config, "snowflake.epoch_ms", "SNOWFLAKE_EPOCH_MS") {
+            settings.epoch_ms = parse_setting("epoch_ms", &v)?;
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), IdError> {
+        if self.worker_id > MAX_WORKER_ID || self.datacenter_id > MAX_DATACENTER_ID {
+            return Err(IdError::InvalidConfig(format!(
+                "worker_id and datacenter_id must be 0..={}, got {} and {}",
+                MAX_WORKER_ID, self.worker_id, self.datacenter_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Fields of a decoded Snowflake id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnowflakeParts {
+    /// Unix milliseconds
+    pub timestamp_ms: u64,
+    /// Datacenter id
+    pub datacenter_id: u16,
+    /// Worker id
+    pub worker_id: u16,
+    /// Per-millisecond sequence number
+    pub sequence: u16,
+}
+
+/// Split a Snowflake id created with `epoch_ms` into its fields
+pub fn snowflake_parts(id: u64, epoch_ms: u64) -> SnowflakeParts {
+    let worker_shift = SEQUENCE_BITS;
+    let datacenter_shift = worker_shift + WORKER_BITS;
+    let timestamp_shift = datacenter_shift + DATACENTER_BITS;
+    SnowflakeParts {
+        timestamp_ms: (id >> timestamp_shift) + epoch_ms,
+        datacenter_id: ((id >> datacenter_shift) & MAX_DATACENTER_ID as u64) as u16,
+        worker_id: ((id >> worker_shift) & MAX_WORKER_ID as u64) as u16,
+        sequence: (id & MAX_SEQUENCE) as u16,
+    }
+}
+
+type Clock = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+/// Thread-safe, lock-free Snowflake id generator
+///
+/// Share one generator per process (e.g. in an `Arc` or a `static`); up to
+/// 4096 ids are issued per millisecond before waiting for the next one.
+#[derive(Clone)]
+pub struct SnowflakeGenerator {
+    config: SnowflakeConfig,
+    /// Last issued `timestamp << 12 | sequence`, relative to the epoch
+    state: Arc<AtomicU64>,
+    clock: Clock,
+}
+
+impl SnowflakeGenerator {
+    /// Generator with the given settings, using the system clock
+    pub fn new(config: SnowflakeConfig) -> Result<Self, IdError> {
+        config.validate()?;
+        Ok(SnowflakeGenerator {
+            config,
+            state: Arc::new(AtomicU64::new(0)),
+            clock: Arc::new(now_millis),
+        })
+    }
+
+    /// Generator configured by [`SnowflakeConfig::from_config`]
+    pub fn from_config(config: &Config) -> Result<Self, IdError> {
+        Self::new(SnowflakeConfig::from_config(config)?)
+    }
+
+    /// Replace the clock (Unix milliseconds), e.g. to simulate rollback in tests
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Settings of this generator
+    pub fn config(&self) -> &SnowflakeConfig {
+        &self.config
+    }
+
+    /// Next id; strictly greater than every id previously returned by this generator
+    pub fn next_id(&self) -> Result<u64, IdError> {
+        let mut waited = Duration::ZERO;
+        loop {
+            let now = self.elapsed()?;
+            let prev = self.state.load(Ordering::Acquire);
+            let prev_ts = prev >> SEQUENCE_BITS;
+
+            let next = if now > prev_ts {
+                now << SEQUENCE_BITS
+            } else if now == prev_ts {
+                if prev & MAX_SEQUENCE == MAX_SEQUENCE {
+                    // Sequence exhausted for this millisecond
+                    std::thread::yield_now();
+                    continue;
+                }
+                prev + 1
+            } else {
+                self.handle_rollback(prev_ts - now, &mut waited)?;
+                continue;
+            };
+
+            if self
+                .state
+                .compare_exchange_weak(prev, next, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return Ok(self.compose(next));
+            }
+        }
+    }
+
+    /// Split an id issued by this generator into its fields
+    pub fn decode(&self, id: u64) -> SnowflakeParts {
+        snowflake_parts(id, self.config.epoch_ms)
+    }
+
+    fn elapsed(&self) -> Result<u64, IdError> {
+        let now = (self.clock)();
+        let elapsed = now
+            .checked_sub(self.config.epoch_ms)
+            .ok_or(IdError::TimestampOutOfRange)?;
+        if elapsed > MAX_TIMESTAMP {
+            return Err(IdError::TimestampOutOfRange);
+        }
+        Ok(elapsed)
+    }
+
+    fn handle_rollback(&self, behind_ms: u64, waited: &mut Duration) -> Result<(), IdError> {
+        let behind = Duration::from_millis(behind_ms);
+        match self.config.clock_rollback {
+            ClockRollback::Wait(max) if *waited + behind <= max => {
+                std::thread::sleep(behind);
+                *waited += behind;
+                Ok(())
+            }
+            _ => Err(IdError::ClockMovedBackwards { behind_ms }),
+        }
+    }
+
+    fn compose(&self, ts_seq: u64) -> u64 {
+        let timestamp = ts_seq >> SEQUENCE_BITS;
+        let sequence = ts_seq & MAX_SEQUENCE;
+        (timestamp << (SEQUENCE_BITS + WORKER_BITS + DATACENTER_BITS))
+            | ((self.config.datacenter_id as u64) << (SEQUENCE_BITS + WORKER_BITS))
+            | ((self.config.worker_id as u64) << SEQUENCE_BITS)
+            | sequence
+    }
+}
+
+impl std::fmt::Debug for SnowflakeGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnowflakeGenerator")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+fn now_millis() -> u64 {
+    current_timestamp_millis() as u64
+}
+
+fn lookup(config: &Config, key: &str, env: &str) -> Option<String> {
+    config
+        .custom
+        .get(key)
+        .cloned()
+        .or_else(|| std::env::var(env).ok())
+}
+
+fn parse_setting<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, IdError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| IdError::InvalidConfig(format!("{} is not a valid number: '{}'", name, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::SeededRandom;
+
+    #[test]
+    fn uuid_versions_and_timestamps() {
+        let v4 = uuid_v4().unwrap();
+        assert_eq!(v4.get_version_num(), 4);
+        assert_eq!(uuid_v7_timestamp(&v4), None);
+
+        let before = now_millis();
+        let v7 = uuid_v7().unwrap();
+        let ts = uuid_v7_timestamp(&v7).unwrap();
+        assert!(ts >= before && ts <= now_millis());
+
+        let a = uuid_v4_with_rng(&mut SeededRandom::from_u64(3)).unwrap();
+        let b = uuid_v4_with_rng(&mut SeededRandom::from_u64(3)).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn ulid_round_trip() {
+        let id = ulid().unwrap();
+        let parsed = Ulid::from_string(&id.to_string()).unwrap();
+        assert_eq!(ulid_timestamp(&parsed), id.timestamp_ms());
+    }
+
+    #[test]
+    fn snowflake_layout() {
+        let config = SnowflakeConfig {
+            datacenter_id: 3,
+            worker_id: 17,
+            ..Default::default()
+        };
+        let generator = SnowflakeGenerator::new(config)
+            .unwrap()
+            .with_clock(|| DEFAULT_SNOWFLAKE_EPOCH_MS + 1_000);
+        let first = generator.next_id().unwrap();
+        let second = generator.next_id().unwrap();
+        assert!(second > first);
+
+        let parts = generator.decode(second);
+        assert_eq!(parts.timestamp_ms, DEFAULT_SNOWFLAKE_EPOCH_MS + 1_000);
+        assert_eq!((parts.datacenter_id, parts.worker_id), (3, 17));
+        assert_eq!(parts.sequence, 1);
+    }
+
+    #[test]
+    fn sequence_overflow_waits_for_next_millisecond() {
+        let now = Arc::new(AtomicU64::new(DEFAULT_SNOWFLAKE_EPOCH_MS + 5));
+        let clock = now.clone();
+        let generator = SnowflakeGenerator::new(SnowflakeConfig::default())
+            .unwrap()
+            .with_clock(move || clock.load(Ordering::SeqCst));
+        for _ in 0..=MAX_SEQUENCE {
+            generator.next_id().unwrap();
+        }
+        now.fetch_add(1, Ordering::SeqCst);
+        let id = generator.next_id().unwrap();
+        assert_eq!(generator.decode(id).sequence, 0);
+        assert_eq!(
+            generator.decode(id).timestamp_ms,
+            DEFAULT_SNOWFLAKE_EPOCH_MS + 6
+        );
+    }
+
+    #[test]
+    fn clock_rollback_policy() {
+        let now = Arc::new(AtomicU64::new(DEFAULT_SNOWFLAKE_EPOCH_MS + 10_000));
+        let clock = now.clone();
+        let config = SnowflakeConfig {
+            clock_rollback: ClockRollback::Error,
+            ..Default::default()
+        };
+        let generator = SnowflakeGenerator::new(config)
+            .unwrap()
+            .with_clock(move || clock.load(Ordering::SeqCst));
+        generator.next_id().unwrap();
+        now.fetch_sub(5, Ordering::SeqCst);
+        assert!(matches!(
+            generator.next_id(),
+            Err(IdError::ClockMovedBackwards { behind_ms: 5 })
+        ));
+    }
+
+    #[test]
+    fn clock_rollback_wait_gives_up_after_limit() {
+        let config = SnowflakeConfig {
+            clock_rollback: ClockRollback::Wait(Duration::from_millis(2)),
+            ..Default::default()
+        };
+        let stuck = SnowflakeGenerator::new(config)
+            .unwrap()
+            .with_clock(|| DEFAULT_SNOWFLAKE_EPOCH_MS + 100);
+        stuck.state.store(101 << SEQUENCE_BITS, Ordering::SeqCst);
+        assert!(matches!(
+            stuck.next_id(),
+            Err(IdError::ClockMovedBackwards { behind_ms: 1 })
+        ));
+    }
+
+    #[test]
+    fn invalid_worker_id() {
+        let config = SnowflakeConfig {
+            worker_id: 32,
+            ..Default::default()
+        };
+        assert!(matches!(
+            SnowflakeGenerator::new(config),
+            Err(IdError::InvalidConfig(_))
+        ));
+    }
+}
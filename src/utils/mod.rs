@@ -89,28 +89,6 @@ pub mod datetime {
     }
 }
 
-/// Validation utilities
-pub mod validation {
-
-    /// Validate email format
-    pub fn is_valid_email(email: &str) -> bool {
-        let re = regex::Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
-        re.is_match(email)
-    }
-
-    /// Validate URL format
-    pub fn is_valid_url(url: &str) -> bool {
-        let re = regex::Regex::new(r"^https?://[^\s/$.?#].[^\s]*$").unwrap();
-        re.is_match(url)
-    }
-
-    /// Validate phone number format (simple check)
-    pub fn is_valid_phone(phone: &str) -> bool {
-        let re = regex::Regex::new(r"^\+?[0-9\s\-()]{10,}$").unwrap();
-        re.is_match(phone)
-    }
-}
-
 /// Collection utilities
 pub mod collection {
     use std::collections::HashMap;
@@ -139,8 +117,17 @@ pub mod collection {
 // Re-export commonly used utilities
 pub use datetime::{current_timestamp, current_timestamp_millis, format_duration};
 pub use string::{is_blank, to_snake_case, truncate_with_ellipsis};
-pub use validation::{is_valid_email, is_valid_phone, is_valid_url};
+pub use validation::{Validator, is_valid_email, is_valid_phone, is_valid_url};
 
+pub mod code_catalog;
+pub mod error_codes;
 pub mod http_code;
+pub mod id;
+pub mod validation;
+pub mod validation_errors;
 pub use http_code::ErrorCode;
-pub use http_code::{StructuredCode, is_valid_code, make_code, parse_code};
+pub use http_code::{
+    StringCode, StructuredCode, is_known_category, is_valid_code, known_categories, make_code,
+    parse_code, register_category,
+};
+pub use validation_errors::{FieldError, Validate, ValidationErrors};
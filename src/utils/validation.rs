@@ -0,0 +1,203 @@
+//! Validation utilities.
+//!
+//! Built-in patterns are compiled once, on first use. For application
+//! patterns, build a [`Validator`] at startup and share it; each pattern is
+//! compiled when it is added, never per call:
+//!
+//! ```
+//! use cland_rust_share::utils::validation::Validator;
+//!
+//! use cland_rust_share::ValidationErrors;
+//!
+//! let validator = Validator::new()
+//!     .with_pattern_rule("sku", r"^SKU-\d{4}$", "sku", "must look like SKU-0042")
+//!     .unwrap();
+//! assert!(validator.is_match("sku", "SKU-0042"));
+//! assert!(validator.is_email("a@example.com"));
+//!
+//! let mut errors = ValidationErrors::new();
+//! validator.check(&mut errors, "items[0].sku", "sku", "sku-42");
+//! assert_eq!(errors.errors()[0].code.as_deref(), Some("sku"));
+//! assert_eq!(errors.errors()[0].message, "must look like SKU-0042");
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::validation_errors::ValidationErrors;
+
+/// Pattern used by [`is_valid_email`]
+pub const EMAIL_PATTERN: &str = r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$";
+
+/// Pattern used by [`is_valid_url`]
+pub const URL_PATTERN: &str = r"^https?://[^\s/$.?#].[^\s]*$";
+
+/// Pattern used by [`is_valid_phone`]
+pub const PHONE_PATTERN: &str = r"^\+?[0-9\s\-()]{10,}$";
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| Regex::new(EMAIL_PATTERN).unwrap());
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(URL_PATTERN).unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| Regex::new(PHONE_PATTERN).unwrap());
+
+/// Validate email format
+pub fn is_valid_email(email: &str) -> bool {
+    EMAIL.is_match(email)
+}
+
+/// Validate URL format
+pub fn is_valid_url(url: &str) -> bool {
+    URL.is_match(url)
+}
+
+/// Validate phone number format (simple check)
+pub fn is_valid_phone(phone: &str) -> bool {
+    PHONE.is_match(phone)
+}
+
+/// Named patterns compiled once and reused for every check
+///
+/// Cheap to share behind an `Arc` or in a `static LazyLock`.
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    patterns: HashMap<String, Pattern>,
+}
+
+/// Compiled pattern and what [`Validator::check`] records when it fails
+#[derive(Debug, Clone)]
+struct Pattern {
+    regex: Regex,
+    code: Cow<'static, str>,
+    message: Cow<'static, str>,
+}
+
+/// Code recorded by patterns added with [`Validator::with_pattern`], as for `#[validate(regex)]`
+pub const PATTERN_CODE: &str = "regex";
+
+/// Message recorded by patterns added with [`Validator::with_pattern`]
+pub const PATTERN_MESSAGE: &str = "invalid format";
+
+impl Validator {
+    /// Validator with only the built-in email, URL and phone checks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile `pattern` and register it as `name`, replacing any previous pattern
+    ///
+    /// Failures are recorded with [`PATTERN_CODE`] and [`PATTERN_MESSAGE`].
+    pub fn with_pattern(
+        self,
+        name: impl Into<String>,
+        pattern: &str,
+    ) -> Result<Self, regex::Error> {
+        self.with_pattern_rule(name, pattern, PATTERN_CODE, PATTERN_MESSAGE)
+    }
+
+    /// Like [`with_pattern`](Self::with_pattern), with the code and message
+    /// recorded by [`check`](Self::check) when the pattern does not match
+    pub fn with_pattern_rule(
+        mut self,
+        name: impl Into<String>,
+        pattern: &str,
+        code: impl Into<Cow<'static, str>>,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Result<Self, regex::Error> {
+        let pattern = Pattern {
+            regex: Regex::new(pattern)?,
+            code: code.into(),
+            message: message.into(),
+        };
+        self.patterns.insert(name.into(), pattern);
+        Ok(self)
+    }
+
+    /// Whether a pattern is registered as `name`
+    pub fn has_pattern(&self, name: &str) -> bool {
+        self.patterns.contains_key(name)
+    }
+
+    /// Whether `value` matches the pattern `name`; `false` for unknown names
+    pub fn is_match(&self, name: &str, value: &str) -> bool {
+        self.patterns
+            .get(name)
+            .is_some_and(|p| p.regex.is_match(value))
+    }
+
+    /// Same as [`is_valid_email`]
+    pub fn is_email(&self, value: &str) -> bool {
+        is_valid_email(value)
+    }
+
+    /// Same as [`is_valid_url`]
+    pub fn is_url(&self, value: &str) -> bool {
+        is_valid_url(value)
+    }
+
+    /// Same as [`is_valid_phone`]
+    pub fn is_phone(&self, value: &str) -> bool {
+        is_valid_phone(value)
+    }
+
+    /// Check `value` against the pattern `name`, recording a failure for
+    /// `field` with the pattern's code and message; returns whether it matched
+    ///
+    /// An unknown `name` is recorded as a failure with [`PATTERN_CODE`] and
+    /// [`PATTERN_MESSAGE`].
+    pub fn check(
+        &self,
+        errors: &mut ValidationErrors,
+        field: impl Into<String>,
+        name: &str,
+        value: &str,
+    ) -> bool {
+        match self.patterns.get(name) {
+            Some(p) => errors.ensure(
+                field,
+                p.regex.is_match(value),
+                p.code.clone(),
+                p.message.clone(),
+            ),
+            None => errors.ensure(field, false, PATTERN_CODE, PATTERN_MESSAGE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_patterns() {
+        assert!(is_valid_email("user.name+tag@example.co"));
+        assert!(!is_valid_email("user@localhost"));
+        assert!(is_valid_url("https://example.com/a?b=c"));
+        assert!(!is_valid_url("example.com"));
+        assert!(is_valid_phone("+86 (010) 1234-5678"));
+        assert!(!is_valid_phone("12345"));
+    }
+
+    #[test]
+    fn validator_patterns() {
+        let validator = Validator::new()
+            .with_pattern("zip", r"^\d{6}$")
+            .unwrap()
+            .with_pattern_rule("sku", r"^SKU-\d+$", "sku", "must look like SKU-123")
+            .unwrap();
+        assert!(validator.has_pattern("zip"));
+        assert!(!validator.is_match("missing", "100000"));
+        assert!(Validator::new().with_pattern("bad", "(").is_err());
+
+        let mut errors = ValidationErrors::new();
+        assert!(validator.check(&mut errors, "zip", "zip", "100000"));
+        assert!(!validator.check(&mut errors, "address.zip", "zip", "1000"));
+        assert!(!validator.check(&mut errors, "items[0].sku", "sku", "123"));
+        assert_eq!(errors.errors()[0].field, "address.zip");
+        assert_eq!(errors.errors()[0].code.as_deref(), Some(PATTERN_CODE));
+        assert_eq!(errors.errors()[0].message, PATTERN_MESSAGE);
+        assert_eq!(errors.errors()[1].code.as_deref(), Some("sku"));
+        assert_eq!(errors.errors()[1].message, "must look like SKU-123");
+    }
+}
@@ -0,0 +1,447 @@
+//! Field-level validation errors, reported in the HTTP message standard's
+//! `{"error_field": ..., "error_detail": ...}` shape.
+//!
+//! Collect every failure instead of stopping at the first one, then turn the
+//! collection into a [`CommonError`] or straight into the `param_error`
+//! envelope:
+//!
+//! ```
+//! use cland_rust_share::utils::validation_errors::{EMAIL, NOT_BLANK, ValidationErrors};
+//!
+//! let mut item = ValidationErrors::new();
+//! item.check("email", "not-an-email", &EMAIL);
+//!
+//! let mut errors = ValidationErrors::new();
+//! errors.check("name", " ", &NOT_BLANK);
+//! errors.nested_at("items", 2, item);
+//!
+//! let body = errors.to_envelope();
+//! assert_eq!(body["msg"], "invalid parameters");
+//! assert_eq!(body["data"][1]["error_field"], "items[2].email");
+//! assert_eq!(body["data"][1]["error_code"], "email");
+//! ```
+//!
+//! The functions in [`utils::validation`](super::validation) are exposed as
+//! [`Rule`]s; [`Rule::new`] wraps any other `fn(&T) -> bool`.
+//!
+//! DTOs can declare their rules with `#[derive(Validate)]` instead:
+//!
+//! ```
+//! use cland_rust_share::{ApiResponse, FieldError, Validate};
+//!
+//! #[derive(Validate)]
+//! struct Item {
+//!     #[validate(range(min = 1, max = 99))]
+//!     qty: u32,
+//! }
+//!
+//! #[derive(Validate)]
+//! struct Order {
+//!     #[validate(email)]
+//!     email: String,
+//!     #[validate(length(min = 1, max = 20), regex = "^[a-z]+$")]
+//!     tag: Option<String>,
+//!     #[validate(nested)]
+//!     items: Vec<Item>,
+//! }
+//!
+//! let order = Order {
+//!     email: "a@example.com".into(),
+//!     tag: None,
+//!     items: vec![Item { qty: 1 }, Item { qty: 0 }],
+//! };
+//! let errors = order.validate().unwrap_err();
+//! assert_eq!(errors.errors()[0].field, "items[1].qty");
+//! assert_eq!(errors.errors()[0].message, "must be between 1 and 99");
+//!
+//! let response: ApiResponse<Vec<FieldError>> = errors.into();
+//! assert_eq!(response.code.http_status(), 400);
+//! ```
+//!
+//! Custom rules are functions taking the field by reference and returning
+//! `Err(message)` on failure: `#[validate(custom(check_sku))]` with
+//! `fn check_sku(sku: &str) -> Result<(), &'static str>`.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::http_code::ErrorCode;
+use super::{is_blank, validation};
+use crate::dto::ApiResponse;
+use crate::error::CommonError;
+
+/// Message used for the envelope and [`CommonError`] built from [`ValidationErrors`]
+pub const VALIDATION_MESSAGE: &str = "invalid parameters";
+
+/// One failed field, shared by [`ValidationErrors`] and [`CommonError::fields`]
+///
+/// `error_code` is omitted for details added with [`CommonError::with_field`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa_support", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    /// Path of the field, e.g. `items[2].email`
+    #[serde(rename = "error_field")]
+    pub field: String,
+    /// Machine-readable rule name, e.g. `email`
+    #[serde(
+        rename = "error_code",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub code: Option<Cow<'static, str>>,
+    /// Message for clients
+    #[serde(rename = "error_detail")]
+    pub message: Cow<'static, str>,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// A named check with the code and message recorded when it fails
+#[derive(Debug)]
+pub struct Rule<T: ?Sized + 'static> {
+    /// Rule name, stored as [`FieldError::code`]
+    pub code: &'static str,
+    /// Message stored on failure
+    pub message: &'static str,
+    /// Returns `true` when the value is valid
+    pub check: fn(&T) -> bool,
+}
+
+impl<T: ?Sized> Rule<T> {
+    /// Rule from any predicate
+    pub const fn new(code: &'static str, message: &'static str, check: fn(&T) -> bool) -> Self {
+        Rule {
+            code,
+            message,
+            check,
+        }
+    }
+}
+
+/// [`validation::is_valid_email`]
+pub const EMAIL: Rule<str> =
+    Rule::new("email", "invalid email address", validation::is_valid_email);
+
+/// [`validation::is_valid_url`]
+pub const URL: Rule<str> = Rule::new("url", "invalid url", validation::is_valid_url);
+
+/// [`validation::is_valid_phone`]
+pub const PHONE: Rule<str> = Rule::new("phone", "invalid phone number", validation::is_valid_phone);
+
+/// Rejects empty and whitespace-only strings
+pub const NOT_BLANK: Rule<str> = Rule::new("required", "must not be blank", |s| !is_blank(s));
+
+/// Every failed check of one request, in the order they were recorded
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    /// Empty collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a failure for `field`
+    pub fn add(
+        &mut self,
+        field: impl Into<String>,
+        code: impl Into<Cow<'static, str>>,
+        message: impl Into<Cow<'static, str>>,
+    ) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            code: Some(code.into()),
+            message: message.into(),
+        });
+    }
+
+    /// Run `rule` on `value`, recording a failure for `field`; returns whether it passed
+    pub fn check<T: ?Sized>(
+        &mut self,
+        field: impl Into<String>,
+        value: &T,
+        rule: &Rule<T>,
+    ) -> bool {
+        let ok = (rule.check)(value);
+        if !ok {
+            self.add(field, rule.code, rule.message);
+        }
+        ok
+    }
+
+    /// Record a failure for `field` unless `ok`; returns `ok`
+    pub fn ensure(
+        &mut self,
+        field: impl Into<String>,
+        ok: bool,
+        code: impl Into<Cow<'static, str>>,
+        message: impl Into<Cow<'static, str>>,
+    ) -> bool {
+        if !ok {
+            self.add(field, code, message);
+        }
+        ok
+    }
+
+    /// Add the errors of a nested object under `prefix`, e.g. `address.city`
+    pub fn nested(&mut self, prefix: &str, child: ValidationErrors) {
+        self.errors.extend(child.errors.into_iter().map(|mut e| {
+            e.field = join_path(prefix, &e.field);
+            e
+        }));
+    }
+
+    /// Add the errors of element `index` of the list `field`, e.g. `items[2].email`
+    pub fn nested_at(&mut self, field: &str, index: usize, child: ValidationErrors) {
+        self.nested(&format!("{}[{}]", field, index), child);
+    }
+
+    /// Recorded errors
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// Whether nothing failed
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Number of recorded errors
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// `Ok(())` when nothing failed, so checks can end with `errors.into_result()?`
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+
+    /// `param_error` envelope with the field list in `data`
+    pub fn to_envelope(&self) -> Value {
+        crate::dto::error_data(ErrorCode::BadRequest, VALIDATION_MESSAGE, &self.errors)
+    }
+}
+
+fn join_path(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() {
+        path.to_string()
+    } else if path.is_empty() {
+        prefix.to_string()
+    } else if path.starts_with('[') {
+        format!("{}{}", prefix, path)
+    } else {
+        format!("{}.{}", prefix, path)
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, e) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", e)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl Extend<FieldError> for ValidationErrors {
+    fn extend<I: IntoIterator<Item = FieldError>>(&mut self, iter: I) {
+        self.errors.extend(iter);
+    }
+}
+
+impl IntoIterator for ValidationErrors {
+    type Item = FieldError;
+    type IntoIter = std::vec::IntoIter<FieldError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+/// Types whose fields can be checked, usually via `#[derive(Validate)]`
+pub trait Validate {
+    /// Every failed check, or `Ok(())`
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+impl<T: Validate + ?Sized> Validate for &T {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        (**self).validate()
+    }
+}
+
+impl<T: Validate + ?Sized> Validate for Box<T> {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        (**self).validate()
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        self.as_ref().map_or(Ok(()), T::validate)
+    }
+}
+
+/// Element errors are reported as `[index].field`
+impl<T: Validate> Validate for [T] {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        for (i, item) in self.iter().enumerate() {
+            if let Err(e) = item.validate() {
+                errors.nested(&format!("[{}]", i), e);
+            }
+        }
+        errors.into_result()
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        self.as_slice().validate()
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use regex::Regex;
+
+    /// Length used by `#[validate(length(..))]`: characters for strings, elements for collections
+    pub trait Length {
+        fn length(&self) -> usize;
+    }
+
+    impl Length for str {
+        fn length(&self) -> usize {
+            self.chars().count()
+        }
+    }
+
+    impl Length for String {
+        fn length(&self) -> usize {
+            self.chars().count()
+        }
+    }
+
+    impl<T> Length for [T] {
+        fn length(&self) -> usize {
+            self.len()
+        }
+    }
+
+    impl<T> Length for Vec<T> {
+        fn length(&self) -> usize {
+            self.len()
+        }
+    }
+
+    impl<K, V, S> Length for std::collections::HashMap<K, V, S> {
+        fn length(&self) -> usize {
+            self.len()
+        }
+    }
+
+    impl<K, V> Length for std::collections::BTreeMap<K, V> {
+        fn length(&self) -> usize {
+            self.len()
+        }
+    }
+
+    pub fn length<T: Length + ?Sized>(value: &T) -> usize {
+        value.length()
+    }
+}
+
+/// `400` error with one field error per failure; the full collection stays
+/// reachable through `source()`
+impl From<ValidationErrors> for CommonError {
+    fn from(errors: ValidationErrors) -> Self {
+        let fields = errors.errors.clone();
+        fields.into_iter().fold(
+            CommonError::new(ErrorCode::BadRequest, VALIDATION_MESSAGE).with_source(errors),
+            CommonError::with_field_error,
+        )
+    }
+}
+
+impl From<ValidationErrors> for ApiResponse<Vec<FieldError>> {
+    fn from(errors: ValidationErrors) -> Self {
+        ApiResponse::error_with_data(ErrorCode::BadRequest, VALIDATION_MESSAGE, errors.errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order() -> ValidationErrors {
+        let mut item = ValidationErrors::new();
+        item.check("email", "nope", &EMAIL);
+        item.ensure("qty", false, "range", "must be at least 1");
+
+        let mut errors = ValidationErrors::new();
+        assert!(errors.check("phone", "+1 (555) 123-4567", &PHONE));
+        errors.check("name", "", &NOT_BLANK);
+        errors.nested_at("items", 2, item);
+        errors
+    }
+
+    #[test]
+    fn nested_paths() {
+        let errors = order();
+        let fields: Vec<&str> = errors.errors().iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["name", "items[2].email", "items[2].qty"]);
+
+        let mut outer = ValidationErrors::new();
+        outer.nested("order", order());
+        assert_eq!(outer.errors()[1].field, "order.items[2].email");
+    }
+
+    #[test]
+    fn envelope_and_common_error() {
+        let errors = order();
+        assert_eq!(
+            errors.to_string(),
+            "name: must not be blank; items[2].email: invalid email address; items[2].qty: must be at least 1"
+        );
+
+        let body = errors.to_envelope();
+        assert_eq!(body["msg"], VALIDATION_MESSAGE);
+        assert_eq!(body["data"][2]["error_detail"], "must be at least 1");
+
+        let err = CommonError::from(errors);
+        assert_eq!(err.http_status(), 400);
+        assert_eq!(err.fields()[1].field, "items[2].email");
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(source.downcast_ref::<ValidationErrors>().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn empty_is_ok() {
+        assert!(ValidationErrors::new().into_result().is_ok());
+        assert!(order().into_result().is_err());
+    }
+
+    #[test]
+    fn response_is_the_same_with_or_without_common_error() {
+        let direct: ApiResponse<Vec<FieldError>> = order().into();
+        let via_error: ApiResponse<Vec<FieldError>> = CommonError::from(order()).into();
+        let direct = serde_json::to_value(direct).unwrap();
+        assert_eq!(direct, serde_json::to_value(via_error).unwrap());
+        assert_eq!(direct["data"][1]["error_code"], "email");
+    }
+}
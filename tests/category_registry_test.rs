@@ -0,0 +1,15 @@
+//! `register_category` changes process-wide state that is never undone, so it
+//! is tested in its own binary where no other test can observe it.
+
+use cland_rust_share::{is_valid_code, make_code, register_category};
+
+#[test]
+fn services_can_add_categories_at_startup() {
+    assert!(!is_valid_code(460));
+    assert!(make_code(460, 1, 2).is_err());
+
+    register_category(460).unwrap();
+    register_category(460).unwrap();
+    assert!(is_valid_code(460));
+    assert_eq!(make_code(460, 1, 2).unwrap(), 46000010002);
+}
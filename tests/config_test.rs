@@ -21,10 +21,16 @@ fn test_config_builder() {
     assert_eq!(config.app.version, "1.0.0");
     assert_eq!(config.app.environment, "test");
     assert!(config.app.debug);
-    assert_eq!(config.database.url, "postgres://localhost:5432/test");
+    assert_eq!(
+        config.database.url.expose_secret(),
+        "postgres://localhost:5432/test"
+    );
     assert_eq!(config.database.max_connections, 20);
     assert_eq!(config.database.timeout_seconds, 60);
-    assert_eq!(config.encryption.key, "test_key_1234567890123456789012");
+    assert_eq!(
+        config.encryption.key.expose_secret(),
+        "test_key_1234567890123456789012"
+    );
     assert_eq!(config.encryption.algorithm, "aes-256-gcm");
     assert_eq!(
         config.custom.get("custom_key"),
@@ -43,7 +49,41 @@ fn test_config_default() {
     assert!(config.database.url.is_empty());
     assert_eq!(config.database.max_connections, 10);
     assert_eq!(config.database.timeout_seconds, 30);
-    assert!(config.encryption.key.is_empty());
+    assert!(config.encryption.key.expose_secret().is_empty());
     assert_eq!(config.encryption.algorithm, "aes-256-gcm");
+    assert!(config.encryption.keys.is_empty());
+    assert!(config.encryption.primary_kid.is_none());
     assert!(config.custom.is_empty());
+    assert!(config.secrets.is_empty());
+}
+
+#[test]
+fn test_config_debug_redacts_secrets() {
+    let config = config::Config::builder()
+        .with_database_url("postgres://app:s3cret-pw@db:5432/main")
+        .with_encryption_key("test_key_1234567890123456789012")
+        .with_encryption_key_entry("v1", "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=")
+        .with_jwt_key("hs", "HS256", "c2lnbmluZy1zZWNyZXQ=")
+        .with_secret("stripe_api_key", "sk_live_abc123")
+        .build();
+
+    let debug = format!("{:?}", config);
+    for leaked in [
+        "s3cret-pw",
+        "test_key_1234567890123456789012",
+        "AQEBAQEB",
+        "c2lnbmluZy1zZWNyZXQ=",
+        "sk_live_abc123",
+    ] {
+        assert!(!debug.contains(leaked), "{} leaked in {}", leaked, debug);
+    }
+    assert!(debug.contains("postgres://app:***@db:5432/main"));
+    assert_eq!(
+        config.database.url.to_string(),
+        "postgres://app:***@db:5432/main"
+    );
+    assert_eq!(
+        config.secrets["stripe_api_key"].expose_secret(),
+        "sk_live_abc123"
+    );
 }
@@ -29,6 +29,38 @@ fn test_aes_invalid_key_length() {
     }
 }
 
+#[test]
+fn test_aes_binary_key_and_aad() {
+    let key: &[u8; 32] = &[7u8; 32];
+
+    let encrypted = crypto::aes_encrypt_with_aad(b"\x00\x01binary", key, b"user:42").unwrap();
+    let decrypted = crypto::aes_decrypt_with_aad(&encrypted, key, b"user:42").unwrap();
+    assert_eq!(decrypted, b"\x00\x01binary");
+
+    // Ciphertext must not contain the key or the plaintext
+    assert!(!encrypted.windows(key.len()).any(|w| w == key));
+    assert!(!encrypted.windows(6).any(|w| w == b"binary"));
+}
+
+#[test]
+fn test_aes_wrong_key_fails() {
+    let encrypted =
+        crypto::aes_encrypt("Hello, World!", "0123456789abcdef0123456789abcdef").unwrap();
+    let result = crypto::aes_decrypt(&encrypted, "fedcba9876543210fedcba9876543210");
+
+    assert!(matches!(
+        result,
+        Err(crypto::CryptoError::OperationFailed(_))
+    ));
+}
+
+#[test]
+fn test_aes_ciphertext_too_short() {
+    let result = crypto::aes_decrypt(&[0u8; 8], "0123456789abcdef0123456789abcdef");
+
+    assert!(matches!(result, Err(crypto::CryptoError::InvalidInput(_))));
+}
+
 #[test]
 fn test_sha256_hash() {
     let data = b"test data";
@@ -61,14 +93,485 @@ fn test_generate_random_string() {
     assert!(random_string.chars().all(|c| c.is_ascii_alphanumeric()));
 }
 
+#[cfg(feature = "insecure-demo")]
 #[test]
 fn test_xor_encryption() {
+    use crypto::obfuscate::{xor_decrypt, xor_encrypt};
+
     let data = b"Hello, World!";
     let key = b"secret_key";
 
-    let encrypted = crypto::xor_encrypt(data, key);
-    let decrypted = crypto::xor_decrypt(&encrypted, key);
+    let encrypted = xor_encrypt(data, key).unwrap();
+    let decrypted = xor_decrypt(&encrypted, key).unwrap();
 
     assert_eq!(decrypted, data);
     assert_ne!(encrypted, data); // Encrypted should be different from original
+    assert!(xor_encrypt(data, b"").is_err());
+}
+
+#[test]
+fn test_id_obfuscation() {
+    let keyring = crypto::Keyring::new("k1", [4u8; 32]).unwrap();
+    let ids = crypto::obfuscate::IdObfuscator::from_keyring(&keyring).unwrap();
+
+    let public: Vec<u64> = (1..=100).map(|id| ids.encode(id)).collect();
+    let mut unique = public.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), 100);
+    for (id, p) in (1..=100).zip(&public) {
+        assert_eq!(ids.decode(*p), id);
+    }
+}
+
+#[test]
+fn test_seal_open_envelope() {
+    let keyring = crypto::Keyring::new("2025-01", [9u8; 32])
+        .unwrap()
+        .with_algorithm(crypto::Algorithm::ChaCha20Poly1305);
+
+    let envelope = crypto::seal(&keyring, b"card=4111").unwrap();
+    assert_eq!(envelope.version(), 1);
+    assert_eq!(envelope.algorithm().as_str(), "chacha20-poly1305");
+    assert_eq!(envelope.key_id(), "2025-01");
+
+    let text = envelope.to_base64();
+    let parsed: crypto::Envelope = text.parse().unwrap();
+    assert_eq!(crypto::open(&keyring, &parsed).unwrap(), b"card=4111");
+
+    let other = crypto::Keyring::new("2024-12", [9u8; 32]).unwrap();
+    assert!(matches!(
+        crypto::open(&other, &parsed),
+        Err(crypto::CryptoError::UnknownKeyId(kid)) if kid == "2025-01"
+    ));
+}
+
+#[test]
+fn test_keyring_from_config_and_reencrypt() {
+    use cland_rust_share::config::Config;
+
+    // "AQEB..." is 32 bytes of 0x01, "AgIC..." is 32 bytes of 0x02
+    let old_key = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+    let new_key = "AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI=";
+
+    let before = Config::builder()
+        .with_encryption_key_entry("v1", old_key)
+        .build();
+    let old_ring = crypto::Keyring::from_config(&before).unwrap();
+    let envelope = crypto::seal(&old_ring, b"archived row").unwrap();
+    assert_eq!(envelope.key_id(), "v1");
+
+    let after = Config::builder()
+        .with_encryption_key_entry("v1", old_key)
+        .with_encryption_key_entry("v2", new_key)
+        .with_primary_kid("v2")
+        .build();
+    let ring = crypto::Keyring::from_config(&after).unwrap();
+    assert_eq!(ring.primary_kid(), "v2");
+
+    // Retired keys still decrypt
+    assert_eq!(crypto::open(&ring, &envelope).unwrap(), b"archived row");
+
+    assert!(ring.needs_reencrypt(&envelope));
+    let upgraded = ring.reencrypt(&envelope).unwrap();
+    assert_eq!(upgraded.key_id(), "v2");
+    assert!(!ring.needs_reencrypt(&upgraded));
+    assert_eq!(crypto::open(&ring, &upgraded).unwrap(), b"archived row");
+}
+
+#[test]
+fn test_keyring_from_config_unknown_primary() {
+    let config = cland_rust_share::Config::builder()
+        .with_encryption_key_entry("v1", "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=")
+        .with_primary_kid("v9")
+        .build();
+
+    assert!(matches!(
+        crypto::Keyring::from_config(&config),
+        Err(crypto::CryptoError::UnknownKeyId(kid)) if kid == "v9"
+    ));
+}
+
+#[test]
+fn test_request_signing() {
+    use crypto::hmac::{SignableRequest, SignatureError, sign_request, verify_request_at};
+
+    let key = b"shared-webhook-secret";
+    let body = br#"{"order_id":42}"#;
+    let request = SignableRequest::new("POST", "/internal/orders", "b1946ac9")
+        .with_query("tenant", "acme")
+        .with_body(body)
+        .with_timestamp(1_700_000_000);
+
+    let signature = sign_request(key, &request);
+    assert_eq!(signature.len(), 64);
+    assert!(verify_request_at(key, &request, &signature, 300, 1_700_000_120).is_ok());
+    let upper = signature.to_uppercase();
+    assert!(verify_request_at(key, &request, &upper, 300, 1_700_000_120).is_ok());
+
+    // Outside the skew window
+    let err = verify_request_at(key, &request, &signature, 300, 1_700_000_301).unwrap_err();
+    assert!(matches!(err, SignatureError::TimestampSkew { .. }));
+    assert_eq!(err.code(), 40090010002);
+
+    // Any change to the request invalidates the signature
+    let tampered = request.clone().with_body(br#"{"order_id":43}"#);
+    let err = verify_request_at(key, &tampered, &signature, 300, 1_700_000_000).unwrap_err();
+    assert_eq!(err, SignatureError::Mismatch);
+    assert!(cland_rust_share::is_valid_code(err.code()));
+
+    assert_eq!(
+        verify_request_at(key, &request, "zz", 300, 1_700_000_000),
+        Err(SignatureError::Malformed)
+    );
+}
+
+#[test]
+fn test_jwt_from_config() {
+    use cland_rust_share::{CommonError, Config};
+    use crypto::jwt::{self, Claims, JwtKeys, Validation};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Session {
+        tenant: String,
+        roles: Vec<String>,
+    }
+
+    let config = Config::builder()
+        // 32-byte Ed25519 seed of 0x01
+        .with_jwt_key(
+            "ed-1",
+            "EdDSA",
+            "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=",
+        )
+        .with_jwt_issuer("auth.internal")
+        .with_jwt_audience("orders")
+        .with_jwt_leeway(30)
+        .build();
+    let keys = JwtKeys::from_config(&config).unwrap();
+    let validation = Validation::from_config(&config);
+
+    let claims = Claims::new(Session {
+        tenant: "acme".to_string(),
+        roles: vec!["admin".to_string()],
+    })
+    .with_issuer("auth.internal")
+    .with_audience("orders")
+    .with_subject("user-7");
+    let claims = Claims {
+        iat: Some(1_700_000_000),
+        ..claims
+    }
+    .expires_in(600);
+    let token = jwt::issue(&keys, &claims).unwrap();
+
+    let decoded: Claims<Session> =
+        jwt::verify_at(&keys, &token, &validation, 1_700_000_100).unwrap();
+    assert_eq!(decoded, claims);
+
+    // Expired beyond the leeway
+    let err = jwt::verify_at::<Session>(&keys, &token, &validation, 1_700_000_631).unwrap_err();
+    assert!(matches!(err, CommonError::TokenExpired));
+    assert_eq!(err.code().to_string(), "40190010101");
+
+    // Within the leeway
+    assert!(jwt::verify_at::<Session>(&keys, &token, &validation, 1_700_000_630).is_ok());
+
+    // Wrong audience
+    let other = Validation {
+        audience: Some("billing".to_string()),
+        ..validation.clone()
+    };
+    let err = jwt::verify_at::<Session>(&keys, &token, &other, 1_700_000_100).unwrap_err();
+    assert!(matches!(err, CommonError::InvalidClaim(_)));
+    assert_eq!(err.code().category, 401);
+}
+
+#[test]
+fn test_jwt_verify_only_config() {
+    use cland_rust_share::Config;
+    use crypto::encoding::base64_encode;
+    use crypto::jwt::{self, Claims, JwtKeys, Validation};
+    use crypto::sign::KeyPair;
+
+    let seed = [1u8; 32];
+    let issuer = JwtKeys::new().with_ed25519("ed-1", KeyPair::from_bytes(&seed).unwrap());
+    let token = jwt::issue(&issuer, &Claims::new(()).expires_in(600)).unwrap();
+
+    // The verifying service only holds the public key
+    let public = KeyPair::from_bytes(&seed).unwrap().public_key().to_bytes();
+    let config = Config::builder()
+        .with_jwt_public_key("ed-1", "EdDSA", base64_encode(public))
+        .build();
+    let keys = JwtKeys::from_config(&config).unwrap();
+    assert!(jwt::verify::<()>(&keys, &token, &Validation::default()).is_ok());
+    assert!(jwt::issue(&keys, &Claims::new(())).is_err());
+
+    let config = Config::builder()
+        .with_jwt_public_key("ed-1", "EdDSA", base64_encode(public))
+        .with_jwt_signing_kid("ed-1")
+        .build();
+    assert!(JwtKeys::from_config(&config).is_err());
+
+    let config = Config::builder()
+        .with_jwt_public_key("hs-1", "HS256", base64_encode([0u8; 32]))
+        .build();
+    assert!(JwtKeys::from_config(&config).is_err());
+}
+
+#[test]
+fn test_jwt_hs256_signature_and_nbf() {
+    use cland_rust_share::CommonError;
+    use crypto::jwt::{self, Claims, JwtKeys, Validation};
+
+    let keys = JwtKeys::new().with_hs256("hs-1", b"an-hs256-secret-of-at-least-32-bytes");
+    let claims = Claims::new(())
+        .expires_in(3600)
+        .not_before(cland_rust_share::utils::current_timestamp() + 600);
+    let token = jwt::issue(&keys, &claims).unwrap();
+
+    let err = jwt::verify::<()>(&keys, &token, &Validation::default()).unwrap_err();
+    assert!(matches!(err, CommonError::TokenNotYetValid));
+
+    // Signed with a different secret under the same kid
+    let attacker = JwtKeys::new().with_hs256("hs-1", b"some-other-secret-of-32-bytes!!!");
+    let forged = jwt::issue(&attacker, &Claims::new(()).expires_in(3600)).unwrap();
+    let err = jwt::verify::<()>(&keys, &forged, &Validation::default()).unwrap_err();
+    assert!(matches!(err, CommonError::InvalidToken(_)));
+
+    let err = jwt::verify::<()>(&keys, "not.a-token", &Validation::default()).unwrap_err();
+    assert!(matches!(err, CommonError::InvalidToken(_)));
+}
+
+#[test]
+fn test_ed25519_sign_verify_and_pem_import() {
+    use crypto::sign::{self, KeyPair, PublicKey};
+
+    let keypair = KeyPair::generate().unwrap();
+    let message = b"release-manifest-v1";
+    let signature = sign::sign(&keypair, message);
+
+    // Verifier only receives the public key as PEM
+    let pem = keypair.public_key().to_public_key_pem().unwrap();
+    let public = PublicKey::from_public_key_pem(&pem).unwrap();
+    assert!(sign::verify(&public, message, &signature).is_ok());
+    assert!(sign::verify(&public, b"release-manifest-v2", &signature).is_err());
+
+    // Streaming verification over a reader
+    assert!(public.verify_reader(&message[..], &signature).is_ok());
+
+    let der = keypair.to_pkcs8_der().unwrap();
+    let restored = KeyPair::from_pkcs8_der(&der).unwrap();
+    assert_eq!(restored.public_key(), public);
+    assert!(matches!(
+        KeyPair::from_pkcs8_pem("not a pem"),
+        Err(crypto::CryptoError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_encrypt_file_round_trip_and_tamper() {
+    use cland_rust_share::CommonError;
+
+    let keyring = crypto::Keyring::new("files-v1", [5u8; 32]).unwrap();
+    let dir = std::env::temp_dir().join(format!("cland-stream-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (plain, sealed, restored) = (
+        dir.join("plain.bin"),
+        dir.join("plain.enc"),
+        dir.join("out.bin"),
+    );
+
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&plain, &data).unwrap();
+
+    assert_eq!(
+        crypto::encrypt_file(&keyring, &plain, &sealed).unwrap(),
+        data.len() as u64
+    );
+    crypto::decrypt_file(&keyring, &sealed, &restored).unwrap();
+    assert_eq!(std::fs::read(&restored).unwrap(), data);
+
+    // Flip one ciphertext byte: decryption fails and leaves no output behind
+    let mut bytes = std::fs::read(&sealed).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0x80;
+    std::fs::write(&sealed, &bytes).unwrap();
+    std::fs::remove_file(&restored).unwrap();
+    let err = crypto::decrypt_file(&keyring, &sealed, &restored).unwrap_err();
+    assert!(matches!(err, CommonError::Crypto(_)));
+    assert!(!restored.exists());
+
+    let err = crypto::encrypt_file(&keyring, dir.join("missing"), &sealed).unwrap_err();
+    assert!(matches!(err, CommonError::Io(_)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_keyring_derive_per_purpose() {
+    let config = cland_rust_share::Config::builder()
+        .with_encryption_key_entry("master-v1", "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=")
+        .build();
+    let master = crypto::Keyring::from_config(&config).unwrap();
+
+    // Field encryption uses its own derived keyring, never the master key
+    let fields = master.derive("db-field").unwrap();
+    let envelope = crypto::seal(&fields, b"4111 1111 1111 1111").unwrap();
+    assert_eq!(envelope.key_id(), "master-v1");
+    assert!(crypto::open(&master, &envelope).is_err());
+    assert_eq!(
+        crypto::open(&fields, &envelope).unwrap(),
+        b"4111 1111 1111 1111"
+    );
+
+    let cookie_key = master.derive_key("cookie-signing").unwrap();
+    let tag = crypto::hmac_sha256(cookie_key.expose_secret(), b"session=abc");
+    assert!(crypto::verify_hmac_sha256(
+        cookie_key.expose_secret(),
+        b"session=abc",
+        &tag
+    ));
+}
+
+#[test]
+fn test_encoding_helpers_on_crypto_outputs() {
+    use crypto::EncodeExt;
+    use crypto::encoding::{self, EncodingError};
+
+    let digest = crypto::sha256_hash(b"abc");
+    let hex = digest.to_hex();
+    assert_eq!(
+        hex,
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert!(encoding::hex_eq(&hex, &hex.to_uppercase()));
+
+    let ciphertext = crypto::aes_encrypt("payload", [1u8; 32].as_slice()).unwrap();
+    let b64 = ciphertext.to_base64();
+    assert_eq!(encoding::base64_decode(&b64).unwrap(), ciphertext);
+    assert!(!ciphertext.to_base64url().contains(['+', '/', '=']));
+
+    assert!(matches!(
+        encoding::base32_decode("MZXW6YQ1"),
+        Err(EncodingError::InvalidSymbol {
+            encoding: "base32",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_random_strings_with_alphabets_and_seeded_rng() {
+    use crypto::{Alphabet, SeededRandom};
+
+    let code = crypto::generate_random_string_from(6, &Alphabet::digits()).unwrap();
+    assert_eq!(code.len(), 6);
+    assert!(code.chars().all(|c| c.is_ascii_digit()));
+
+    let token = crypto::generate_random_string_from(32, &Alphabet::url_safe()).unwrap();
+    assert!(
+        token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    );
+
+    // Same seed, same output: reproducible tokens and ciphertexts in tests
+    let alphabet = Alphabet::unambiguous();
+    let a = crypto::generate_random_string_with_rng(16, &alphabet, &mut SeededRandom::from_u64(1));
+    let b = crypto::generate_random_string_with_rng(16, &alphabet, &mut SeededRandom::from_u64(1));
+    assert_eq!(a.unwrap(), b.unwrap());
+
+    let key = [3u8; 32];
+    let c1 = crypto::aes_encrypt_with_rng(b"x", key, b"", &mut SeededRandom::from_u64(9)).unwrap();
+    let c2 = crypto::aes_encrypt_with_rng(b"x", key, b"", &mut SeededRandom::from_u64(9)).unwrap();
+    assert_eq!(c1, c2);
+
+    let s1 = crypto::otp::Totp::generate_secret_with_rng(&mut SeededRandom::from_u64(5)).unwrap();
+    let s2 = crypto::otp::Totp::generate_secret_with_rng(&mut SeededRandom::from_u64(5)).unwrap();
+    assert_eq!(s1, s2);
+    assert_eq!(s1.len(), crypto::otp::DEFAULT_SECRET_LEN);
+}
+
+#[test]
+fn test_totp_provisioning_and_replay() {
+    use crypto::otp::{OtpAlgorithm, Totp};
+
+    let secret = Totp::generate_secret().unwrap();
+    let totp = Totp::new(secret).unwrap();
+    let uri = totp.provisioning_uri("Acme Corp", "alice@example.com");
+    assert!(uri.starts_with("otpauth://totp/Acme%20Corp:alice%40example.com?secret="));
+    assert!(uri.ends_with("&issuer=Acme%20Corp&algorithm=SHA1&digits=6&period=30"));
+
+    // The base32 secret shown to the user restores the same generator
+    let restored = Totp::from_base32(&totp.secret_base32().to_lowercase()).unwrap();
+    let now = 1_700_000_000;
+    let code = restored.generate_at(now);
+    assert_eq!(code, totp.generate_at(now));
+
+    let used = totp.verify_at(&code, now + 20, None).unwrap();
+    assert!(totp.verify_at(&code, now + 20, Some(used)).is_none());
+
+    let sha256 = Totp::new(b"12345678901234567890123456789012".to_vec())
+        .unwrap()
+        .with_algorithm(OtpAlgorithm::Sha256);
+    assert!(
+        sha256
+            .provisioning_uri("x", "y")
+            .contains("algorithm=SHA256")
+    );
+}
+
+#[test]
+fn test_encrypted_fields_survive_rotation() {
+    use crypto::field::{self, EncryptedField, Searchable};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Customer {
+        name: String,
+        phone: Searchable<String>,
+        id_card: EncryptedField<String>,
+    }
+
+    let mut keyring = crypto::Keyring::new("k1", [1u8; 32]).unwrap();
+    field::install_keyring(&keyring).unwrap();
+    assert!(field::is_installed());
+
+    let customer = Customer {
+        name: "Alice".to_string(),
+        phone: Searchable::new("13800000000".to_string()),
+        id_card: EncryptedField::new("110101199001011234".to_string()),
+    };
+    let stored = serde_json::to_value(&customer).unwrap();
+    let phone_ct = stored["phone"].as_str().unwrap().to_string();
+    assert!(!stored.to_string().contains("13800000000"));
+    assert!(!format!("{:?}", customer).contains("110101199001011234"));
+
+    // Lookup by the deterministic ciphertext of the search term
+    let needle = Searchable::new("13800000000".to_string())
+        .ciphertext()
+        .unwrap();
+    assert_eq!(needle, phone_ct);
+    assert_eq!(
+        field::blind_index("13800000000").unwrap(),
+        customer.phone.blind_index().unwrap()
+    );
+
+    // After rotation old rows still decrypt, and reseal moves them to the new key
+    keyring.rotate("k2", [2u8; 32]).unwrap();
+    field::install_keyring(&keyring).unwrap();
+    let loaded: Customer = serde_json::from_value(stored).unwrap();
+    assert_eq!(*loaded.phone, "13800000000");
+    assert_eq!(loaded.id_card.as_str(), "110101199001011234");
+
+    let resealed = Searchable::<String>::reseal(&phone_ct).unwrap();
+    assert_ne!(resealed, phone_ct);
+    assert_eq!(
+        resealed,
+        Searchable::new("13800000000".to_string())
+            .ciphertext()
+            .unwrap()
+    );
 }
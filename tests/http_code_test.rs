@@ -21,3 +21,43 @@ fn validity_checks() {
     assert!(is_valid_code(40010010001i64));
     assert!(!is_valid_code(201));
 }
+
+#[test]
+fn extended_categories() {
+    use cland_rust_share::ErrorCode;
+
+    for short in [401, 403, 404, 409, 422, 429, 502, 503, 504] {
+        assert!(is_valid_code(short), "{short}");
+        assert!(ErrorCode::from_category(short as i32).is_some());
+    }
+    assert_eq!(
+        parse_code(make_code(409, 2001, 3).unwrap())
+            .unwrap()
+            .category,
+        409
+    );
+}
+
+#[test]
+fn structured_code_conversions_and_serde() {
+    use std::collections::BTreeSet;
+
+    let code: StructuredCode = "40010010001".parse().unwrap();
+    assert_eq!(code.http_status(), 400);
+    assert_eq!(StructuredCode::try_from(404i64).unwrap().http_status(), 404);
+    assert_eq!(i64::from(code), 40010010001);
+    assert!("4001".parse::<StructuredCode>().is_err());
+
+    // Usable directly in DTOs, accepting either JSON form
+    #[derive(serde::Deserialize)]
+    struct Upstream {
+        code: StructuredCode,
+    }
+    let a: Upstream = serde_json::from_str(r#"{"code":"50020030002"}"#).unwrap();
+    let b: Upstream = serde_json::from_str(r#"{"code":50020030002}"#).unwrap();
+    assert_eq!(a.code, b.code);
+    assert_eq!(a.code.http_status(), 500);
+
+    let sorted: BTreeSet<StructuredCode> = [code, a.code, "200".parse().unwrap()].into();
+    assert_eq!(sorted.first().unwrap().category, 200);
+}
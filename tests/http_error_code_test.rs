@@ -4,7 +4,16 @@ use cland_rust_share::ErrorCode;
 fn error_code_values() {
     assert_eq!(ErrorCode::Ok.value(), 200);
     assert_eq!(ErrorCode::BadRequest.value(), 400);
+    assert_eq!(ErrorCode::Unauthorized.value(), 401);
+    assert_eq!(ErrorCode::Forbidden.value(), 403);
+    assert_eq!(ErrorCode::NotFound.value(), 404);
+    assert_eq!(ErrorCode::Conflict.value(), 409);
+    assert_eq!(ErrorCode::UnprocessableEntity.value(), 422);
+    assert_eq!(ErrorCode::TooManyRequests.value(), 429);
     assert_eq!(ErrorCode::Internal.value(), 500);
+    assert_eq!(ErrorCode::BadGateway.value(), 502);
+    assert_eq!(ErrorCode::ServiceUnavailable.value(), 503);
+    assert_eq!(ErrorCode::GatewayTimeout.value(), 504);
 }
 
 #[test]
@@ -14,3 +23,61 @@ fn error_code_structured() {
     assert_eq!(s.system, 0);
     assert_eq!(s.detail, 0);
 }
+
+#[test]
+fn error_code_registry_catches_clashes() {
+    use cland_rust_share::error::{TokenCode, builtin_codes};
+    use cland_rust_share::utils::error_codes::RegistryError;
+    use cland_rust_share::{StructuredCode, define_error_codes};
+
+    define_error_codes! {
+        /// A service that picked the crate's own system id by mistake
+        enum AuthCode: 9001 {
+            Locked(401, 104) => "account locked",
+        }
+    }
+
+    define_error_codes! {
+        enum OrderCode: 1001 {
+            NotFound(400, 1) => "order not found",
+        }
+    }
+
+    let registry = builtin_codes().unwrap();
+    assert_eq!(registry.get(9001, 101).unwrap().name, "Expired");
+    assert!(matches!(
+        registry.clone().register::<AuthCode>(),
+        Err(RegistryError::Duplicate {
+            system: 9001,
+            detail: 104,
+            ..
+        })
+    ));
+
+    let registry = registry.register::<OrderCode>().unwrap();
+    assert_eq!(registry.len(), 8);
+
+    let code: StructuredCode = OrderCode::NotFound.into();
+    assert_eq!(code.to_string(), "40010010001");
+    assert_eq!(TokenCode::Expired.to_string(), "token expired");
+}
+
+#[test]
+fn error_code_catalog_export() {
+    use cland_rust_share::error::builtin_codes;
+    use cland_rust_share::utils::code_catalog::Catalog;
+
+    let catalog = Catalog::from_registry(&builtin_codes().unwrap());
+    assert_eq!(catalog.entries().len(), 7);
+    assert_eq!(catalog.locales(), vec!["zh-CN"]);
+
+    let expired = &catalog.to_json()[3];
+    assert_eq!(expired["code"], 40190010101i64);
+    assert_eq!(expired["name"], "TokenCode::Expired");
+    assert_eq!(expired["locales"]["zh-CN"], "令牌已过期");
+
+    assert!(catalog.to_markdown().contains(
+        "| 40190010101 | 401 | 9001 | 0101 | `TokenCode::Expired` | token expired | 令牌已过期 |"
+    ));
+    assert!(catalog.to_openapi_extension()["x-error-codes"].is_array());
+}
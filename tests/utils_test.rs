@@ -84,3 +84,46 @@ fn test_collection_utilities() {
     assert!(collection::all_unique::<i32>(&[])); // Empty vector is unique
     assert!(collection::all_unique(&["a", "b", "c"]));
 }
+
+#[test]
+fn test_id_generation() {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use utils::id::{self, SnowflakeGenerator};
+
+    let v7 = id::uuid_v7().unwrap();
+    assert!(id::uuid_v7_timestamp(&v7).unwrap() > 0);
+    assert_eq!(id::ulid().unwrap().to_string().len(), 26);
+
+    let config = cland_rust_share::Config::builder()
+        .with_custom("snowflake.worker_id", "7")
+        .with_custom("snowflake.datacenter_id", "2")
+        .build();
+    let generator = Arc::new(SnowflakeGenerator::from_config(&config).unwrap());
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let generator = generator.clone();
+            std::thread::spawn(move || {
+                (0..5_000)
+                    .map(|_| generator.next_id().unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let mut all = HashSet::new();
+    for handle in handles {
+        let ids = handle.join().unwrap();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        all.extend(ids);
+    }
+    assert_eq!(all.len(), 20_000);
+
+    let parts = generator.decode(*all.iter().next().unwrap());
+    assert_eq!((parts.datacenter_id, parts.worker_id), (2, 7));
+
+    let bad = cland_rust_share::Config::builder()
+        .with_custom("snowflake.worker_id", "99")
+        .build();
+    assert!(SnowflakeGenerator::from_config(&bad).is_err());
+}
@@ -0,0 +1,132 @@
+//! Integration tests for `#[derive(Validate)]`
+
+use cland_rust_share::{ApiResponse, CommonError, ErrorCode, FieldError, Validate};
+
+fn check_sku(sku: &str) -> Result<(), &'static str> {
+    if sku.starts_with("SKU-") {
+        Ok(())
+    } else {
+        Err("must start with SKU-")
+    }
+}
+
+#[derive(Validate)]
+struct Address {
+    #[validate(length(min = 2))]
+    city: String,
+}
+
+#[derive(Validate)]
+struct Item {
+    #[validate(custom(check_sku))]
+    sku: String,
+    #[validate(range(min = 1, max = 99, code = "qty"))]
+    qty: u32,
+}
+
+#[derive(Validate)]
+struct Signup {
+    #[validate(email)]
+    email: String,
+    #[validate(phone(message = "请输入正确的手机号"))]
+    phone: Option<String>,
+    #[validate(url)]
+    homepage: Option<String>,
+    #[validate(length(max = 8), regex = "^[a-z0-9_]+$")]
+    username: String,
+    #[validate(range(min = 0.0, max = 1.0))]
+    ratio: f64,
+    #[validate(nested)]
+    address: Option<Address>,
+    #[validate(nested, length(min = 1, max = 3))]
+    items: Vec<Item>,
+    #[allow(dead_code)]
+    note: String,
+}
+
+fn valid() -> Signup {
+    Signup {
+        email: "a@example.com".into(),
+        phone: Some("+86 138 0013 8000".into()),
+        homepage: None,
+        username: "alice_1".into(),
+        ratio: 0.5,
+        address: Some(Address {
+            city: "Shanghai".into(),
+        }),
+        items: vec![Item {
+            sku: "SKU-1".into(),
+            qty: 1,
+        }],
+        note: String::new(),
+    }
+}
+
+#[test]
+fn valid_struct_passes() {
+    assert!(valid().validate().is_ok());
+}
+
+#[test]
+fn every_rule_reports_its_field() {
+    let signup = Signup {
+        email: "nope".into(),
+        phone: Some("123".into()),
+        homepage: Some("ftp://x".into()),
+        username: "Bad Name!".into(),
+        ratio: 1.5,
+        address: Some(Address { city: "X".into() }),
+        items: vec![
+            Item {
+                sku: "SKU-1".into(),
+                qty: 1,
+            },
+            Item {
+                sku: "1".into(),
+                qty: 100,
+            },
+        ],
+        note: String::new(),
+    };
+    let errors = signup.validate().unwrap_err();
+    let got: Vec<(&str, &str, &str)> = errors
+        .errors()
+        .iter()
+        .map(|e| {
+            (
+                e.field.as_str(),
+                e.code.as_deref().unwrap(),
+                e.message.as_ref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        got,
+        [
+            ("email", "email", "invalid email address"),
+            ("phone", "phone", "请输入正确的手机号"),
+            ("homepage", "url", "invalid url"),
+            ("username", "length", "length must be at most 8"),
+            ("username", "regex", "invalid format"),
+            ("ratio", "range", "must be between 0 and 1"),
+            ("address.city", "length", "length must be at least 2"),
+            ("items[1].sku", "custom", "must start with SKU-"),
+            ("items[1].qty", "qty", "must be between 1 and 99"),
+        ]
+    );
+}
+
+#[test]
+fn errors_become_bad_request() {
+    let mut signup = valid();
+    signup.items.clear();
+    let errors = signup.validate().unwrap_err();
+    assert_eq!(errors.errors()[0].message, "length must be between 1 and 3");
+
+    let response: ApiResponse<Vec<FieldError>> = errors.clone().into();
+    assert_eq!(response.code, ErrorCode::BadRequest.as_structured());
+    assert_eq!(response.data.unwrap()[0].field, "items");
+
+    let err: CommonError = errors.into();
+    assert_eq!(err.http_status(), 400);
+}